@@ -0,0 +1,42 @@
+//! Running closures from UIKit controls and bar buttons.
+use std::ptr::NonNull;
+
+use block2::{Block, RcBlock};
+use objc2::rc::Retained;
+use objc2_foundation::{MainThreadMarker, NSString};
+use objc2_ui_kit::{UIAction, UIBarButtonItem, UIBarButtonSystemItem, UIImage};
+
+/// An action that calls `f` when performed.
+pub fn action(mtm: MainThreadMarker, f: impl Fn() + 'static) -> Retained<UIAction> {
+    let block = RcBlock::new(move |_: NonNull<UIAction>| f());
+    let block_ptr: *const Block<_> = &*block;
+    unsafe { UIAction::actionWithHandler(block_ptr.cast_mut(), mtm) }
+}
+
+/// A system bar button that calls `f` when tapped.
+pub fn bar_button_item(
+    system_item: UIBarButtonSystemItem,
+    mtm: MainThreadMarker,
+    f: impl Fn() + 'static,
+) -> Retained<UIBarButtonItem> {
+    unsafe {
+        UIBarButtonItem::initWithBarButtonSystemItem_primaryAction(
+            mtm.alloc(),
+            system_item,
+            Some(&action(mtm, f)),
+        )
+    }
+}
+
+/// A bar button showing the named SF Symbol that calls `f` when tapped.
+pub fn image_bar_button_item(
+    image_name: &NSString,
+    mtm: MainThreadMarker,
+    f: impl Fn() + 'static,
+) -> Retained<UIBarButtonItem> {
+    let action = action(mtm, f);
+    unsafe {
+        action.setImage(UIImage::systemImageNamed(image_name).as_deref());
+        UIBarButtonItem::initWithPrimaryAction(mtm.alloc(), Some(&action))
+    }
+}
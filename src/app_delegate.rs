@@ -6,8 +6,14 @@ use objc2_ui_kit::{
     UISceneSession,
 };
 
+use crate::library::Library;
+
 #[derive(Debug)]
-pub struct Ivars {}
+pub struct Ivars {
+    /// The library shared by all scenes, or `None` if there is no data
+    /// directory to store it in.
+    library: Option<Library>,
+}
 
 declare_class!(
     #[derive(Debug)]
@@ -29,7 +35,11 @@ declare_class!(
         // Called by UIKitApplicationMain
         #[method_id(init)]
         fn init(this: Allocated<Self>) -> Retained<Self> {
-            let this = this.set_ivars(Ivars {});
+            let library = Library::default_root().map(Library::new);
+            if library.is_none() {
+                tracing::error!("no data directory, the library is unavailable");
+            }
+            let this = this.set_ivars(Ivars { library });
             unsafe { msg_send_id![super(this), init] }
         }
     }
@@ -73,3 +83,20 @@ declare_class!(
         }
     }
 );
+
+impl AppDelegate {
+    /// The application's delegate, if it is this class.
+    pub fn shared(mtm: MainThreadMarker) -> Option<Retained<Self>> {
+        let delegate = unsafe { UIApplication::sharedApplication(mtm).delegate() }?;
+        if delegate.isKindOfClass(Self::class()) {
+            // SAFETY: Just checked that the delegate is an `AppDelegate`
+            Some(unsafe { Retained::cast(delegate) })
+        } else {
+            None
+        }
+    }
+
+    pub fn library(&self) -> Option<&Library> {
+        self.ivars().library.as_ref()
+    }
+}
@@ -0,0 +1,587 @@
+//! Running many movies headless, to catch regressions in Ruffle.
+//!
+//! Each movie runs in its own process, so that a movie that hangs can be
+//! killed, and one that crashes doesn't take the rest of the run with it.
+use std::cell::RefCell;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::rc::Rc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use ruffle_core::backend::log::LogBackend;
+use ruffle_frontend_utils::bundle::info::BUNDLE_INFORMATION_FILENAME;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::cli::PlayerArgs;
+use crate::headless::{run_headless, RunLimit, RunSummary};
+use crate::launch::{panic_message, Launch, LaunchError};
+
+/// A movie to run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchMovie {
+    /// How the movie is shown in the report.
+    pub name: String,
+    /// The path or URL to open.
+    pub source: String,
+}
+
+/// Find the movies to run.
+///
+/// `input` is either a directory, which is searched recursively for SWFs and
+/// Ruffle Bundles, or a manifest file with a path or URL on each line. Empty
+/// lines and lines starting with `#` are ignored, and relative paths are
+/// relative to the manifest.
+pub fn collect_movies(input: &Path) -> io::Result<Vec<BatchMovie>> {
+    let mut movies = vec![];
+    if input.is_dir() {
+        collect_dir(input, input, &mut movies)?;
+        movies.sort_by(|a, b| a.name.cmp(&b.name));
+        return Ok(movies);
+    }
+
+    let base = input.parent().unwrap_or(Path::new(""));
+    for line in fs::read_to_string(input)?.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let source = if Url::parse(line).is_ok() || Path::new(line).is_absolute() {
+            line.to_string()
+        } else {
+            base.join(line).to_string_lossy().into_owned()
+        };
+        movies.push(BatchMovie {
+            name: line.to_string(),
+            source,
+        });
+    }
+    Ok(movies)
+}
+
+fn collect_dir(root: &Path, dir: &Path, movies: &mut Vec<BatchMovie>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let is_bundle_dir = path.join(BUNDLE_INFORMATION_FILENAME).is_file();
+        let is_movie_file = path.is_file()
+            && path.extension().is_some_and(|ext| {
+                ext.eq_ignore_ascii_case("swf") || ext.eq_ignore_ascii_case("ruf")
+            });
+
+        if is_bundle_dir || is_movie_file {
+            let name = path.strip_prefix(root).unwrap_or(&path);
+            movies.push(BatchMovie {
+                name: name.to_string_lossy().into_owned(),
+                source: path.to_string_lossy().into_owned(),
+            });
+        } else if path.is_dir() {
+            collect_dir(root, &path, movies)?;
+        }
+    }
+    Ok(())
+}
+
+/// How often to check whether a movie's process has exited.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+#[derive(Debug, Clone)]
+pub struct BatchOptions {
+    /// The number of frames to run each movie for.
+    pub frames: u32,
+    /// How long a movie may take to load and run, including starting its
+    /// process.
+    pub timeout: Duration,
+    /// Applied to every movie.
+    pub player: PlayerArgs,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchStatus {
+    Passed,
+    /// The movie couldn't be opened or loaded.
+    LoadError,
+    /// The player panicked, or the movie's process crashed.
+    Panicked,
+    TimedOut,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchResult {
+    pub name: String,
+    pub status: BatchStatus,
+    /// The error or panic message, if it didn't pass.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    pub duration_ms: f64,
+    pub ticks: u32,
+    pub mean_tick_ms: f64,
+    pub max_tick_ms: f64,
+    pub trace_lines: u64,
+    pub warnings: u64,
+    /// A hash of the `trace()` output, to compare between runs.
+    pub trace_hash: String,
+}
+
+impl BatchResult {
+    fn new(movie: &BatchMovie, status: BatchStatus, message: Option<String>) -> Self {
+        Self {
+            name: movie.name.clone(),
+            status,
+            message,
+            duration_ms: 0.0,
+            ticks: 0,
+            mean_tick_ms: 0.0,
+            max_tick_ms: 0.0,
+            trace_lines: 0,
+            warnings: 0,
+            trace_hash: format!("{:016x}", TraceStats::default().hash),
+        }
+    }
+
+    pub fn passed(&self) -> bool {
+        self.status == BatchStatus::Passed
+    }
+}
+
+/// Run the movies one after another, calling `on_result` after each.
+///
+/// Each movie runs in a child process started with `command`, which is
+/// given the movie and the path to write its result to. The child must pass
+/// these on to [`run_child`].
+pub fn run_batch(
+    movies: &[BatchMovie],
+    options: &BatchOptions,
+    command: impl Fn(&BatchMovie, &Path) -> Command,
+    mut on_result: impl FnMut(&BatchResult),
+) -> Vec<BatchResult> {
+    movies
+        .iter()
+        .enumerate()
+        .map(|(index, movie)| {
+            let result_path = std::env::temp_dir()
+                .join(format!("ruffle-batch-{}-{index}.json", std::process::id()));
+            let result = run_in_child(movie, options, command(movie, &result_path), &result_path);
+            if let Err(err) = fs::remove_file(&result_path) {
+                if err.kind() != io::ErrorKind::NotFound {
+                    tracing::warn!(?result_path, "failed removing movie result: {err}");
+                }
+            }
+            on_result(&result);
+            result
+        })
+        .collect()
+}
+
+/// Run a movie in this process, and write the result to `result_path` for
+/// [`run_batch`] to read.
+pub fn run_child(movie: &BatchMovie, options: &BatchOptions, result_path: &Path) -> io::Result<()> {
+    let result = run_movie(movie, options);
+    fs::write(result_path, serde_json::to_vec(&result)?)
+}
+
+fn run_in_child(
+    movie: &BatchMovie,
+    options: &BatchOptions,
+    mut command: Command,
+    result_path: &Path,
+) -> BatchResult {
+    tracing::info!(movie = movie.name, "running movie");
+    let start = Instant::now();
+    let failed = |status, message: String| {
+        let mut result = BatchResult::new(movie, status, Some(message));
+        result.duration_ms = millis(start.elapsed());
+        result
+    };
+
+    // The child reports through `result_path`, and stdout is for our report
+    let spawned = command.stdin(Stdio::null()).stdout(Stdio::null()).spawn();
+    let mut child = match spawned {
+        Ok(child) => child,
+        Err(err) => {
+            let message = format!("could not start the movie's process: {err}");
+            return failed(BatchStatus::LoadError, message);
+        }
+    };
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if start.elapsed() < options.timeout => thread::sleep(POLL_INTERVAL),
+            Ok(None) => {
+                tracing::error!(movie = movie.name, "movie timed out");
+                kill(&mut child);
+                let message = format!("did not finish within {:?}", options.timeout);
+                return failed(BatchStatus::TimedOut, message);
+            }
+            Err(err) => {
+                kill(&mut child);
+                let message = format!("failed waiting for the movie's process: {err}");
+                return failed(BatchStatus::Panicked, message);
+            }
+        }
+    };
+
+    let res =
+        fs::read(result_path).and_then(|data| Ok(serde_json::from_slice::<BatchResult>(&data)?));
+    match res {
+        Ok(result) => BatchResult {
+            name: movie.name.clone(),
+            ..result
+        },
+        // E.g. killed by a signal, or aborted while panicking
+        Err(err) => {
+            let message =
+                format!("the movie's process exited with {status} without a result: {err}");
+            failed(BatchStatus::Panicked, message)
+        }
+    }
+}
+
+fn kill(child: &mut std::process::Child) {
+    if let Err(err) = child.kill() {
+        tracing::error!("failed killing the movie's process: {err}");
+    }
+    // Reap it, so that it doesn't linger as a zombie
+    let _ = child.wait();
+}
+
+fn run_movie(movie: &BatchMovie, options: &BatchOptions) -> BatchResult {
+    let start = Instant::now();
+    let trace = Rc::new(RefCell::new(TraceStats::default()));
+    let res = run_headless_movie(&movie.source, options, trace.clone());
+    let trace = trace.take();
+
+    let mut result = match res {
+        Ok(summary) => {
+            let mut result = BatchResult::new(movie, BatchStatus::Passed, None);
+            result.ticks = summary.ticks;
+            if summary.ticks > 0 {
+                result.mean_tick_ms = millis(summary.tick_time) / f64::from(summary.ticks);
+            }
+            result.max_tick_ms = millis(summary.max_tick_time);
+            result
+        }
+        Err(LaunchError::Panicked(message)) => {
+            BatchResult::new(movie, BatchStatus::Panicked, Some(message))
+        }
+        Err(err) => BatchResult::new(movie, BatchStatus::LoadError, Some(err.to_string())),
+    };
+    result.duration_ms = millis(start.elapsed());
+    result.trace_lines = trace.lines;
+    result.warnings = trace.warnings;
+    result.trace_hash = format!("{:016x}", trace.hash);
+    result
+}
+
+fn run_headless_movie(
+    source: &str,
+    options: &BatchOptions,
+    trace: Rc<RefCell<TraceStats>>,
+) -> Result<RunSummary, LaunchError> {
+    let mut launch = Launch::from_path_or_url(source)?;
+    options.player.apply(&mut launch);
+    // `run_headless` only catches panics in the player itself, not while
+    // loading the movie.
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        run_headless(
+            &launch,
+            RunLimit::Frames(options.frames),
+            TraceLogBackend(trace),
+        )
+    }))
+    .unwrap_or_else(|payload| Err(LaunchError::Panicked(panic_message(&*payload))))
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+#[derive(Debug)]
+struct TraceStats {
+    lines: u64,
+    warnings: u64,
+    /// FNV-1a, since it's stable across Rust versions and platforms, unlike
+    /// the hasher in `std`.
+    hash: u64,
+}
+
+impl Default for TraceStats {
+    fn default() -> Self {
+        Self {
+            lines: 0,
+            warnings: 0,
+            hash: 0xcbf29ce484222325,
+        }
+    }
+}
+
+impl TraceStats {
+    fn add_line(&mut self, line: &str) {
+        self.lines += 1;
+        for byte in line.bytes().chain([b'\n']) {
+            self.hash ^= u64::from(byte);
+            self.hash = self.hash.wrapping_mul(0x100000001b3);
+        }
+    }
+}
+
+struct TraceLogBackend(Rc<RefCell<TraceStats>>);
+
+impl LogBackend for TraceLogBackend {
+    fn avm_trace(&self, message: &str) {
+        self.0.borrow_mut().add_line(message);
+    }
+
+    fn avm_warning(&self, message: &str) {
+        tracing::debug!("AVM warning: {message}");
+        self.0.borrow_mut().warnings += 1;
+    }
+}
+
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    passed: usize,
+    failed: usize,
+    movies: &'a [BatchResult],
+}
+
+pub fn write_json(results: &[BatchResult], writer: impl Write) -> io::Result<()> {
+    let passed = results.iter().filter(|result| result.passed()).count();
+    let report = JsonReport {
+        passed,
+        failed: results.len() - passed,
+        movies: results,
+    };
+    serde_json::to_writer_pretty(writer, &report)?;
+    Ok(())
+}
+
+/// Write a JUnit XML report, as understood by most CI systems.
+///
+/// Panics and timeouts are reported as failures, while load errors are
+/// reported as errors.
+pub fn write_junit(results: &[BatchResult], mut writer: impl Write) -> io::Result<()> {
+    let count = |status| results.iter().filter(|r| r.status == status).count();
+    let failures = count(BatchStatus::Panicked) + count(BatchStatus::TimedOut);
+    let errors = count(BatchStatus::LoadError);
+    let time: f64 = results.iter().map(|result| result.duration_ms).sum();
+
+    writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        writer,
+        r#"<testsuite name="ruffle" tests="{}" failures="{failures}" errors="{errors}" time="{:.3}">"#,
+        results.len(),
+        time / 1000.0,
+    )?;
+    for result in results {
+        writeln!(
+            writer,
+            r#"  <testcase classname="ruffle" name="{}" time="{:.3}">"#,
+            xml_escape(&result.name),
+            result.duration_ms / 1000.0,
+        )?;
+        let message = xml_escape(result.message.as_deref().unwrap_or_default());
+        let problem = match result.status {
+            BatchStatus::Passed => None,
+            BatchStatus::LoadError => Some(("error", "load_error")),
+            BatchStatus::Panicked => Some(("failure", "panicked")),
+            BatchStatus::TimedOut => Some(("failure", "timed_out")),
+        };
+        if let Some((element, kind)) = problem {
+            writeln!(
+                writer,
+                r#"    <{element} type="{kind}" message="{message}"/>"#
+            )?;
+        }
+        writeln!(
+            writer,
+            "    <system-out>ticks={} mean_tick_ms={:.3} max_tick_ms={:.3} trace_lines={} warnings={} trace_hash={}</system-out>",
+            result.ticks,
+            result.mean_tick_ms,
+            result.max_tick_ms,
+            result.trace_lines,
+            result.warnings,
+            result.trace_hash,
+        )?;
+        writeln!(writer, "  </testcase>")?;
+    }
+    writeln!(writer, "</testsuite>")
+}
+
+fn xml_escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\n' => escaped.push_str("&#10;"),
+            // Not allowed in XML 1.0
+            c if c.is_control() && c != '\t' && c != '\r' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(name: &str, status: BatchStatus, message: Option<&str>) -> BatchResult {
+        let movie = BatchMovie {
+            name: name.to_string(),
+            source: name.to_string(),
+        };
+        BatchResult::new(&movie, status, message.map(str::to_string))
+    }
+
+    fn names(movies: &[BatchMovie]) -> Vec<&str> {
+        movies.iter().map(|movie| movie.name.as_str()).collect()
+    }
+
+    #[test]
+    fn collect_dir_sorted() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("a/nested")).unwrap();
+        fs::create_dir_all(root.join("z bundle/content")).unwrap();
+        for file in [
+            "b.swf",
+            "a/c.SWF",
+            "a/nested/d.swf",
+            "x.ruf",
+            "notes.txt",
+            "z bundle/content/movie.swf",
+        ] {
+            fs::write(root.join(file), "").unwrap();
+        }
+        fs::write(root.join("z bundle").join(BUNDLE_INFORMATION_FILENAME), "").unwrap();
+
+        let movies = collect_movies(root).unwrap();
+        // Bundles are run as a whole, not the SWFs inside them
+        assert_eq!(
+            names(&movies),
+            ["a/c.SWF", "a/nested/d.swf", "b.swf", "x.ruf", "z bundle"]
+        );
+        assert_eq!(movies[2].source, root.join("b.swf").to_string_lossy());
+    }
+
+    #[test]
+    fn collect_manifest() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest = dir.path().join("manifest.txt");
+        fs::write(
+            &manifest,
+            "# Regression tests\n\nmovies/a.swf\n  https://example.com/b.swf  \n/absolute/c.swf\n",
+        )
+        .unwrap();
+
+        let movies = collect_movies(&manifest).unwrap();
+        // In the order of the manifest
+        assert_eq!(
+            names(&movies),
+            [
+                "movies/a.swf",
+                "https://example.com/b.swf",
+                "/absolute/c.swf"
+            ]
+        );
+        assert_eq!(
+            movies[0].source,
+            dir.path().join("movies/a.swf").to_string_lossy()
+        );
+        assert_eq!(movies[1].source, "https://example.com/b.swf");
+        assert_eq!(movies[2].source, "/absolute/c.swf");
+    }
+
+    #[test]
+    fn escape() {
+        assert_eq!(
+            xml_escape(r#"<a href="x">Tom & 'Jerry'</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;Tom &amp; &apos;Jerry&apos;&lt;/a&gt;"
+        );
+        // Newlines are kept in attributes, other control characters can't be
+        // represented in XML 1.0
+        assert_eq!(xml_escape("a\nb\tc\r"), "a&#10;b\tc\r");
+        assert_eq!(xml_escape("\u{0}bell\u{7}\u{1b}[0m"), "bell[0m");
+    }
+
+    #[test]
+    fn junit() {
+        let mut load_error = result("missing.swf", BatchStatus::LoadError, Some("not found"));
+        load_error.duration_ms = 500.0;
+        let results = [
+            result("passed.swf", BatchStatus::Passed, None),
+            load_error,
+            result("panicked.swf", BatchStatus::Panicked, Some("index < len")),
+            result("slow.swf", BatchStatus::TimedOut, Some("did not finish")),
+        ];
+        let mut output = vec![];
+        write_junit(&results, &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert!(output.starts_with(r#"<?xml version="1.0" encoding="UTF-8"?>"#));
+        assert!(output.contains(
+            r#"<testsuite name="ruffle" tests="4" failures="2" errors="1" time="0.500">"#
+        ));
+        assert_eq!(output.matches("<testcase ").count(), 4);
+        assert_eq!(output.matches("</testcase>").count(), 4);
+        assert!(output.contains(r#"<error type="load_error" message="not found"/>"#));
+        assert!(output.contains(r#"<failure type="panicked" message="index &lt; len"/>"#));
+        assert!(output.contains(r#"<failure type="timed_out" message="did not finish"/>"#));
+        // Only the failed movies have a problem element
+        let passed = output
+            .split("<testcase ")
+            .find(|case| case.contains(r#"name="passed.swf""#))
+            .unwrap();
+        assert!(!passed.contains("<failure") && !passed.contains("<error"));
+        assert!(output.trim_end().ends_with("</testsuite>"));
+    }
+
+    #[test]
+    fn json() {
+        let results = [
+            result("passed.swf", BatchStatus::Passed, None),
+            result("slow.swf", BatchStatus::TimedOut, Some("did not finish")),
+        ];
+        let mut output = vec![];
+        write_json(&results, &mut output).unwrap();
+        let report: serde_json::Value = serde_json::from_slice(&output).unwrap();
+        assert_eq!(report["passed"], 1);
+        assert_eq!(report["failed"], 1);
+        assert_eq!(report["movies"][0]["status"], "passed");
+        assert!(report["movies"][0].get("message").is_none());
+        assert_eq!(report["movies"][1]["status"], "timed_out");
+        assert_eq!(report["movies"][1]["message"], "did not finish");
+
+        // Read back by `run_batch` from the child processes
+        let result: BatchResult = serde_json::from_value(report["movies"][0].clone()).unwrap();
+        assert_eq!(result.name, "passed.swf");
+        assert_eq!(result.message, None);
+    }
+
+    #[test]
+    fn trace_hash() {
+        assert_eq!(
+            result("movie.swf", BatchStatus::Passed, None).trace_hash,
+            "cbf29ce484222325"
+        );
+
+        let trace = Rc::new(RefCell::new(TraceStats::default()));
+        let log = TraceLogBackend(trace.clone());
+        log.avm_trace("hello");
+        log.avm_warning("ignored in the hash");
+        log.avm_trace("world");
+        let trace = trace.take();
+        assert_eq!(trace.lines, 2);
+        assert_eq!(trace.warnings, 1);
+        // FNV-1a of "hello\nworld\n"
+        assert_eq!(trace.hash, 0x2925835410388969);
+    }
+}
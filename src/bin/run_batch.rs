@@ -0,0 +1,149 @@
+//! Run many movies headless, and write a report of how it went.
+//!
+//! Meant for catching regressions in CI, so it doesn't need a GPU or a
+//! window, and exits with a non-zero status if any movie failed.
+use std::env;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitCode};
+use std::time::Duration;
+
+use clap::{Parser, ValueEnum};
+use ruffle_ios::batch::{
+    collect_movies, run_batch, run_child, write_json, write_junit, BatchMovie, BatchOptions,
+};
+use ruffle_ios::cli::PlayerArgs;
+use ruffle_ios::init_logging;
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Format {
+    Json,
+    Junit,
+}
+
+#[derive(Debug, Parser)]
+#[command(about = "Run many SWFs headless and report the results")]
+struct Args {
+    /// A directory to search for SWFs and Ruffle Bundles, or a manifest file
+    /// with a path or URL on each line.
+    input: PathBuf,
+
+    /// Number of frames to run each movie for.
+    #[arg(long, default_value_t = 100)]
+    frames: u32,
+
+    /// How many seconds each movie may take to load and run.
+    #[arg(long, default_value_t = 30)]
+    timeout: u64,
+
+    /// The format of the report.
+    #[arg(long, value_enum, default_value_t = Format::Json)]
+    format: Format,
+
+    /// Where to write the report, instead of stdout.
+    #[arg(long, short)]
+    output: Option<PathBuf>,
+
+    #[command(flatten)]
+    player: PlayerArgs,
+
+    /// Run only this movie, and write its result to `--result`. Used for the
+    /// process each movie runs in.
+    #[arg(long, hide = true, value_name = "SOURCE", requires = "result")]
+    single_movie: Option<String>,
+
+    #[arg(long, hide = true, value_name = "PATH")]
+    result: Option<PathBuf>,
+}
+
+fn main() -> ExitCode {
+    init_logging();
+    let args = Args::parse();
+    let options = BatchOptions {
+        frames: args.frames,
+        timeout: Duration::from_secs(args.timeout),
+        player: args.player.clone(),
+    };
+
+    if let (Some(source), Some(result_path)) = (&args.single_movie, &args.result) {
+        return run_single_movie(source, &options, result_path);
+    }
+
+    let movies = match collect_movies(&args.input) {
+        Ok(movies) => movies,
+        Err(err) => {
+            eprintln!("error: failed reading {}: {err}", args.input.display());
+            return ExitCode::FAILURE;
+        }
+    };
+    if movies.is_empty() {
+        eprintln!("error: no movies found in {}", args.input.display());
+        return ExitCode::FAILURE;
+    }
+
+    let exe = match env::current_exe() {
+        Ok(exe) => exe,
+        Err(err) => {
+            eprintln!("error: failed finding the run_batch executable: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let command = |movie: &BatchMovie, result_path: &Path| {
+        let mut command = Command::new(&exe);
+        // Before the parent's arguments, in case those end with `--`
+        command
+            .arg("--single-movie")
+            .arg(&movie.source)
+            .arg("--result")
+            .arg(result_path)
+            .args(env::args_os().skip(1));
+        command
+    };
+    let results = run_batch(&movies, &options, command, |result| {
+        eprintln!("{:?}: {}", result.status, result.name);
+    });
+
+    let mut writer: Box<dyn Write> = match &args.output {
+        Some(path) => match File::create(path) {
+            Ok(file) => Box::new(BufWriter::new(file)),
+            Err(err) => {
+                eprintln!("error: failed creating {}: {err}", path.display());
+                return ExitCode::FAILURE;
+            }
+        },
+        None => Box::new(io::stdout().lock()),
+    };
+    let res = match args.format {
+        Format::Json => write_json(&results, &mut writer),
+        Format::Junit => write_junit(&results, &mut writer),
+    }
+    // Dropping a `BufWriter` would ignore the error
+    .and_then(|()| writer.flush());
+    if let Err(err) = res {
+        eprintln!("error: failed writing report: {err}");
+        return ExitCode::FAILURE;
+    }
+
+    let failed = results.iter().filter(|result| !result.passed()).count();
+    eprintln!("{} passed, {failed} failed", results.len() - failed);
+    if failed == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+fn run_single_movie(source: &str, options: &BatchOptions, result_path: &Path) -> ExitCode {
+    let movie = BatchMovie {
+        name: source.to_string(),
+        source: source.to_string(),
+    };
+    match run_child(&movie, options, result_path) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: failed writing {}: {err}", result_path.display());
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -1,79 +1,184 @@
 //! Run an SWF without setting up navigation, a data model and everything.
-use std::cell::OnceCell;
+//!
+//! With `--headless`, the movie is run without a window, printing its
+//! `trace()` output to stdout. That is the only mode on other platforms than
+//! iOS.
+use std::process::ExitCode;
+use std::time::Duration;
+
+use clap::Parser;
+use ruffle_core::backend::log::LogBackend;
+use ruffle_ios::cli::PlayerArgs;
+use ruffle_ios::headless::{run_headless, RunLimit};
+use ruffle_ios::{init_logging, Launch};
+
+#[derive(Debug, Parser)]
+#[command(about = "Run an SWF")]
+struct Args {
+    /// The movie to run: a path or URL to an SWF, or a path to a Ruffle
+    /// Bundle, either a `.ruf` file or an unpacked directory.
+    ///
+    /// The options of a bundle apply, unless overridden by the options below.
+    movie: String,
+
+    /// Run without a window, printing `trace()` output to stdout.
+    ///
+    /// Exits with a non-zero status if the movie fails to load, or if the
+    /// player panics.
+    #[arg(long)]
+    headless: bool,
+
+    /// Number of frames to run when headless.
+    #[arg(long, default_value_t = 100, conflicts_with = "seconds")]
+    frames: u32,
+
+    /// Run for this many seconds in real time when headless, instead of a
+    /// number of frames.
+    #[arg(long)]
+    seconds: Option<f64>,
+
+    #[command(flatten)]
+    player: PlayerArgs,
+}
 
-use objc2::rc::{Allocated, Retained};
-use objc2::{declare_class, msg_send_id, mutability, ClassType, DeclaredClass};
-use objc2_foundation::{MainThreadMarker, NSObject, NSObjectProtocol};
-use objc2_ui_kit::{UIApplication, UIApplicationDelegate, UIScreen, UIWindow};
+#[cfg(target_os = "ios")]
+mod app {
+    use std::cell::OnceCell;
 
-use ruffle_ios::{init_logging, launch, PlayerController};
+    use clap::Parser;
+    use objc2::rc::{Allocated, Retained};
+    use objc2::{declare_class, msg_send_id, mutability, ClassType, DeclaredClass};
+    use objc2_foundation::{MainThreadMarker, NSObject, NSObjectProtocol};
+    use objc2_ui_kit::{UIApplication, UIApplicationDelegate, UIScreen, UIWindow};
+    use ruffle_ios::{Launch, PlayerController};
 
-#[derive(Debug)]
-pub struct Ivars {
-    window: OnceCell<Retained<UIWindow>>,
-}
+    use super::Args;
 
-declare_class!(
     #[derive(Debug)]
-    pub struct AppDelegate;
-
-    unsafe impl ClassType for AppDelegate {
-        type Super = NSObject;
-        type Mutability = mutability::MainThreadOnly;
-        const NAME: &'static str = "AppDelegate";
+    pub struct Ivars {
+        window: OnceCell<Retained<UIWindow>>,
     }
 
-    impl DeclaredClass for AppDelegate {
-        type Ivars = Ivars;
-    }
+    declare_class!(
+        #[derive(Debug)]
+        pub struct AppDelegate;
 
-    unsafe impl NSObjectProtocol for AppDelegate {}
+        unsafe impl ClassType for AppDelegate {
+            type Super = NSObject;
+            type Mutability = mutability::MainThreadOnly;
+            const NAME: &'static str = "AppDelegate";
+        }
 
-    unsafe impl AppDelegate {
-        // Called by UIKitApplicationMain
-        #[method_id(init)]
-        fn init(this: Allocated<Self>) -> Retained<Self> {
-            let this = this.set_ivars(Ivars {
-                window: OnceCell::new(),
-            });
-            unsafe { msg_send_id![super(this), init] }
+        impl DeclaredClass for AppDelegate {
+            type Ivars = Ivars;
         }
-    }
 
-    unsafe impl UIApplicationDelegate for AppDelegate {
-        #[method(applicationDidFinishLaunching:)]
-        fn did_finish_launching(&self, _application: &UIApplication) {
-            tracing::info!("applicationDidFinishLaunching:");
-            self.setup();
+        unsafe impl NSObjectProtocol for AppDelegate {}
+
+        unsafe impl AppDelegate {
+            // Called by UIKitApplicationMain
+            #[method_id(init)]
+            fn init(this: Allocated<Self>) -> Retained<Self> {
+                let this = this.set_ivars(Ivars {
+                    window: OnceCell::new(),
+                });
+                unsafe { msg_send_id![super(this), init] }
+            }
         }
-    }
-);
 
-impl AppDelegate {
-    fn setup(&self) {
-        let movie_url = std::env::args().skip(1).next();
-        let movie_url = movie_url.expect("must provide a path or URL to an SWF to run");
-        let mtm = MainThreadMarker::from(self);
+        unsafe impl UIApplicationDelegate for AppDelegate {
+            #[method(applicationDidFinishLaunching:)]
+            fn did_finish_launching(&self, _application: &UIApplication) {
+                tracing::info!("applicationDidFinishLaunching:");
+                self.setup();
+            }
+        }
+    );
+
+    impl AppDelegate {
+        fn setup(&self) {
+            // Already validated in `main`
+            let args = Args::parse();
+            let mut movie = Launch::from_path_or_url(&args.movie).expect("failed opening movie");
+            args.player.apply(&mut movie);
+            let mtm = MainThreadMarker::from(self);
 
-        #[allow(deprecated)] // Unsure how else we should do this when setting up?
-        let frame = UIScreen::mainScreen(mtm).bounds();
+            #[allow(deprecated)] // Unsure how else we should do this when setting up?
+            let frame = UIScreen::mainScreen(mtm).bounds();
 
-        let window = unsafe { UIWindow::initWithFrame(mtm.alloc(), frame) };
+            let window = unsafe { UIWindow::initWithFrame(mtm.alloc(), frame) };
 
-        let view_controller = PlayerController::new(mtm, movie_url);
-        window.setRootViewController(Some(&view_controller));
+            let view_controller = PlayerController::new(mtm, movie);
+            window.setRootViewController(Some(&view_controller));
 
-        window.makeKeyAndVisible();
+            window.makeKeyAndVisible();
 
-        self.ivars()
-            .window
-            .set(window)
-            .expect("can only initialize once");
+            self.ivars()
+                .window
+                .set(window)
+                .expect("can only initialize once");
+        }
     }
 }
 
-#[tokio::main]
-async fn main() {
+/// Prints AVM output, so that it can be compared or piped elsewhere.
+struct PrintLogBackend;
+
+impl LogBackend for PrintLogBackend {
+    fn avm_trace(&self, message: &str) {
+        println!("{message}");
+    }
+
+    fn avm_warning(&self, message: &str) {
+        eprintln!("warning: {message}");
+    }
+}
+
+fn run(args: &Args) -> ExitCode {
+    let limit = match args.seconds {
+        Some(seconds) => match Duration::try_from_secs_f64(seconds) {
+            Ok(duration) => RunLimit::Duration(duration),
+            Err(err) => {
+                eprintln!("error: invalid number of seconds {seconds}: {err}");
+                return ExitCode::FAILURE;
+            }
+        },
+        None => RunLimit::Frames(args.frames),
+    };
+
+    let mut launch = match Launch::from_path_or_url(&args.movie) {
+        Ok(launch) => launch,
+        Err(err) => {
+            eprintln!("error: failed opening movie: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+    args.player.apply(&mut launch);
+
+    match run_headless(&launch, limit, PrintLogBackend) {
+        Ok(summary) => {
+            tracing::info!(ticks = summary.ticks, "ran movie in {:?}", summary.elapsed);
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn main() -> ExitCode {
     init_logging();
-    launch(None, Some(AppDelegate::class()));
+    let args = Args::parse();
+
+    if args.headless || !cfg!(target_os = "ios") {
+        return run(&args);
+    }
+
+    #[cfg(target_os = "ios")]
+    {
+        use objc2::ClassType;
+        ruffle_ios::launch(None, Some(app::AppDelegate::class()));
+    }
+    ExitCode::SUCCESS
 }
@@ -0,0 +1,531 @@
+//! Command-line flags for player options, mirroring desktop Ruffle.
+//!
+//! The flag values use the same names as desktop Ruffle, and the names from
+//! `ruffle-bundle.toml` are accepted too.
+use std::time::Duration;
+
+use ruffle_core::backend::navigator::{OpenURLMode, SocketMode};
+use ruffle_core::config::Letterbox;
+use ruffle_core::{LoadBehavior, PlayerRuntime, StageAlign, StageScaleMode};
+use ruffle_render::quality::StageQuality;
+use url::Url;
+
+use crate::launch::Launch;
+use crate::settings::{
+    letterbox_to_str, load_behavior_to_str, open_url_mode_to_str, quality_to_str, runtime_to_str,
+    scale_to_str, socket_mode_to_str,
+};
+
+/// Overrides for the movie's player options and settings.
+///
+/// Options that aren't given are left as they are, so that the options from a
+/// bundle still apply. Boolean flags may be given as `--flag=false` to turn
+/// off an option that the bundle turns on.
+#[derive(Debug, Clone, Default, clap::Args)]
+pub struct PlayerArgs {
+    /// A movie parameter (FlashVar), may be given multiple times.
+    #[arg(short = 'P', long = "parameter", value_name = "NAME=VALUE", value_parser = parse_parameter)]
+    pub parameters: Vec<(String, String)>,
+
+    /// How to scale the movie: exact-fit, no-border, no-scale or show-all.
+    #[arg(long, value_parser = parse_scale)]
+    pub scale: Option<StageScaleMode>,
+
+    /// Prevent the movie from changing the scale mode, or allow it with
+    /// `--force-scale=false`.
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub force_scale: Option<bool>,
+
+    /// How to align the movie, any combination of T, B, L and R, or empty to
+    /// center it.
+    #[arg(long, value_parser = parse_align)]
+    pub align: Option<StageAlign>,
+
+    /// Prevent the movie from changing the alignment, or allow it with
+    /// `--force-align=false`.
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub force_align: Option<bool>,
+
+    /// The rendering quality: low, medium, high, best, high8x8,
+    /// high8x8-linear, high16x16 or high16x16-linear.
+    #[arg(long, value_parser = parse_quality)]
+    pub quality: Option<StageQuality>,
+
+    /// Whether to hide content outside the stage: off, fullscreen or on.
+    #[arg(long, value_parser = parse_letterbox)]
+    pub letterbox: Option<Letterbox>,
+
+    /// Override the movie's frame rate.
+    #[arg(long, value_parser = parse_frame_rate)]
+    pub frame_rate: Option<f64>,
+
+    /// The Flash Player version to emulate.
+    #[arg(long, value_parser = clap::value_parser!(u8).range(1..=32))]
+    pub player_version: Option<u8>,
+
+    /// The runtime to emulate: flash-player or air.
+    #[arg(long, value_parser = parse_runtime)]
+    pub player_runtime: Option<PlayerRuntime>,
+
+    /// The URL that relative URLs in the movie are resolved against.
+    #[arg(long)]
+    pub base: Option<Url>,
+
+    /// The URL that the movie thinks it's running from.
+    #[arg(long)]
+    pub spoof_url: Option<Url>,
+
+    /// The referer to send with requests.
+    #[arg(long)]
+    pub referer: Option<Url>,
+
+    /// The cookie to send with requests.
+    #[arg(long)]
+    pub cookie: Option<String>,
+
+    /// Upgrade HTTP requests to HTTPS, or don't with
+    /// `--upgrade-to-https=false`.
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub upgrade_to_https: Option<bool>,
+
+    /// How to load the movie: streaming, delayed or blocking.
+    #[arg(long, value_parser = parse_load_behavior)]
+    pub load_behavior: Option<LoadBehavior>,
+
+    /// How long scripts may run before they're stopped, in seconds.
+    #[arg(long, value_name = "SECONDS")]
+    pub max_execution_duration: Option<u64>,
+
+    /// Provide a dummy ExternalInterface, for movies that require one, or
+    /// don't with `--dummy-external-interface=false`.
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub dummy_external_interface: Option<bool>,
+
+    /// Whether the movie may open web pages: allow, confirm or deny.
+    #[arg(long, value_parser = parse_open_url_mode)]
+    pub open_url_mode: Option<OpenURLMode>,
+
+    /// Whether the movie may open TCP connections: allow, ask or deny.
+    #[arg(long = "tcp-connections", alias = "socket-mode", value_parser = parse_socket_mode)]
+    pub socket_mode: Option<SocketMode>,
+
+    /// Allow TCP connections to this address, may be given multiple times.
+    #[arg(long, value_name = "HOST:PORT", value_parser = parse_socket_address)]
+    pub socket_allow: Vec<String>,
+}
+
+impl PlayerArgs {
+    /// Apply the options that were given on top of those of the launch.
+    pub fn apply(&self, launch: &mut Launch) {
+        let options = &mut launch.info.player;
+        for (name, value) in &self.parameters {
+            options.parameters.retain(|(existing, _)| existing != name);
+            options.parameters.push((name.clone(), value.clone()));
+        }
+        if self.scale.is_some() {
+            options.scale = self.scale;
+        }
+        if self.force_scale.is_some() {
+            options.force_scale = self.force_scale;
+        }
+        if self.align.is_some() {
+            options.align = self.align;
+        }
+        if self.force_align.is_some() {
+            options.force_align = self.force_align;
+        }
+        if self.quality.is_some() {
+            options.quality = self.quality;
+        }
+        if self.letterbox.is_some() {
+            options.letterbox = self.letterbox;
+        }
+        if self.frame_rate.is_some() {
+            options.frame_rate = self.frame_rate;
+        }
+        if self.player_version.is_some() {
+            options.player_version = self.player_version;
+        }
+        if self.player_runtime.is_some() {
+            options.player_runtime = self.player_runtime;
+        }
+        if self.base.is_some() {
+            options.base.clone_from(&self.base);
+        }
+        if self.spoof_url.is_some() {
+            options.spoof_url.clone_from(&self.spoof_url);
+        }
+        if self.referer.is_some() {
+            options.referer.clone_from(&self.referer);
+        }
+        if self.cookie.is_some() {
+            options.cookie.clone_from(&self.cookie);
+        }
+        if self.upgrade_to_https.is_some() {
+            options.upgrade_to_https = self.upgrade_to_https;
+        }
+        if self.load_behavior.is_some() {
+            options.load_behavior = self.load_behavior;
+        }
+        if let Some(secs) = self.max_execution_duration {
+            options.max_execution_duration = Some(Duration::from_secs(secs));
+        }
+        if self.dummy_external_interface.is_some() {
+            options.dummy_external_interface = self.dummy_external_interface;
+        }
+
+        let settings = &mut launch.settings;
+        if self.open_url_mode.is_some() {
+            settings.open_url_mode = self.open_url_mode;
+        }
+        if self.socket_mode.is_some() {
+            settings.socket_mode = self.socket_mode;
+        }
+        for address in &self.socket_allow {
+            settings.socket_denied.retain(|denied| denied != address);
+            if !settings.socket_allowed.contains(address) {
+                settings.socket_allowed.push(address.clone());
+            }
+        }
+    }
+}
+
+/// Find the variant named `s`, either by its name in desktop Ruffle's flags,
+/// or by its name in `ruffle-bundle.toml`.
+fn parse_choice<T: Copy>(
+    s: &str,
+    variants: &[(T, &'static str)],
+    to_str: fn(T) -> &'static str,
+) -> Result<T, String> {
+    variants
+        .iter()
+        .find(|&&(variant, name)| {
+            name.eq_ignore_ascii_case(s) || to_str(variant).eq_ignore_ascii_case(s)
+        })
+        .map(|&(variant, _)| variant)
+        .ok_or_else(|| {
+            let names: Vec<_> = variants.iter().map(|&(_, name)| name).collect();
+            format!("expected one of: {}", names.join(", "))
+        })
+}
+
+fn parse_scale(s: &str) -> Result<StageScaleMode, String> {
+    parse_choice(
+        s,
+        &[
+            (StageScaleMode::ExactFit, "exact-fit"),
+            (StageScaleMode::NoBorder, "no-border"),
+            (StageScaleMode::NoScale, "no-scale"),
+            (StageScaleMode::ShowAll, "show-all"),
+        ],
+        scale_to_str,
+    )
+}
+
+fn parse_quality(s: &str) -> Result<StageQuality, String> {
+    parse_choice(
+        s,
+        &[
+            (StageQuality::Low, "low"),
+            (StageQuality::Medium, "medium"),
+            (StageQuality::High, "high"),
+            (StageQuality::Best, "best"),
+            (StageQuality::High8x8, "high8x8"),
+            (StageQuality::High8x8Linear, "high8x8-linear"),
+            (StageQuality::High16x16, "high16x16"),
+            (StageQuality::High16x16Linear, "high16x16-linear"),
+        ],
+        quality_to_str,
+    )
+}
+
+fn parse_letterbox(s: &str) -> Result<Letterbox, String> {
+    parse_choice(
+        s,
+        &[
+            (Letterbox::Off, "off"),
+            (Letterbox::Fullscreen, "fullscreen"),
+            (Letterbox::On, "on"),
+        ],
+        letterbox_to_str,
+    )
+}
+
+fn parse_runtime(s: &str) -> Result<PlayerRuntime, String> {
+    parse_choice(
+        s,
+        &[
+            (PlayerRuntime::FlashPlayer, "flash-player"),
+            (PlayerRuntime::AIR, "air"),
+        ],
+        runtime_to_str,
+    )
+}
+
+fn parse_load_behavior(s: &str) -> Result<LoadBehavior, String> {
+    parse_choice(
+        s,
+        &[
+            (LoadBehavior::Streaming, "streaming"),
+            (LoadBehavior::Delayed, "delayed"),
+            (LoadBehavior::Blocking, "blocking"),
+        ],
+        load_behavior_to_str,
+    )
+}
+
+fn parse_open_url_mode(s: &str) -> Result<OpenURLMode, String> {
+    parse_choice(
+        s,
+        &[
+            (OpenURLMode::Allow, "allow"),
+            (OpenURLMode::Confirm, "confirm"),
+            (OpenURLMode::Deny, "deny"),
+        ],
+        open_url_mode_to_str,
+    )
+}
+
+fn parse_socket_mode(s: &str) -> Result<SocketMode, String> {
+    parse_choice(
+        s,
+        &[
+            (SocketMode::Allow, "allow"),
+            (SocketMode::Ask, "ask"),
+            (SocketMode::Deny, "deny"),
+        ],
+        socket_mode_to_str,
+    )
+}
+
+fn parse_align(s: &str) -> Result<StageAlign, String> {
+    let mut align = StageAlign::empty();
+    for c in s.chars() {
+        align |= match c.to_ascii_uppercase() {
+            'T' => StageAlign::TOP,
+            'B' => StageAlign::BOTTOM,
+            'L' => StageAlign::LEFT,
+            'R' => StageAlign::RIGHT,
+            _ => return Err(format!("unknown alignment {c:?}, expected T, B, L or R")),
+        };
+    }
+    Ok(align)
+}
+
+fn parse_frame_rate(s: &str) -> Result<f64, String> {
+    let frame_rate: f64 = s.parse().map_err(|err| format!("{err}"))?;
+    if frame_rate.is_finite() && frame_rate > 0.0 {
+        Ok(frame_rate)
+    } else {
+        Err("must be a positive number".into())
+    }
+}
+
+fn parse_parameter(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((name, value)) if !name.is_empty() => Ok((name.to_string(), value.to_string())),
+        _ => Err("expected NAME=VALUE".into()),
+    }
+}
+
+fn parse_socket_address(s: &str) -> Result<String, String> {
+    match s.rsplit_once(':') {
+        Some((host, port)) if !host.is_empty() && port.parse::<u16>().is_ok() => Ok(s.to_string()),
+        _ => Err("expected HOST:PORT".into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        player: PlayerArgs,
+    }
+
+    fn parse(args: &[&str]) -> Result<PlayerArgs, clap::Error> {
+        let args = std::iter::once("test").chain(args.iter().copied());
+        Cli::try_parse_from(args).map(|cli| cli.player)
+    }
+
+    #[test]
+    fn choice() {
+        assert!(matches!(
+            parse_scale("show-all"),
+            Ok(StageScaleMode::ShowAll)
+        ));
+        assert!(matches!(
+            parse_quality("high8x8-linear"),
+            Ok(StageQuality::High8x8Linear)
+        ));
+        assert!(matches!(
+            parse_runtime("flash-player"),
+            Ok(PlayerRuntime::FlashPlayer)
+        ));
+        assert!(matches!(
+            parse_load_behavior("delayed"),
+            Ok(LoadBehavior::Delayed)
+        ));
+        assert!(matches!(parse_socket_mode("ask"), Ok(SocketMode::Ask)));
+        // Case doesn't matter
+        assert!(matches!(
+            parse_scale("No-Border"),
+            Ok(StageScaleMode::NoBorder)
+        ));
+    }
+
+    #[test]
+    fn choice_bundle_names() {
+        assert!(matches!(
+            parse_scale("showall"),
+            Ok(StageScaleMode::ShowAll)
+        ));
+        assert!(matches!(
+            parse_scale("NoBorder"),
+            Ok(StageScaleMode::NoBorder)
+        ));
+        assert!(matches!(
+            parse_quality("8x8linear"),
+            Ok(StageQuality::High8x8Linear)
+        ));
+        assert!(matches!(
+            parse_runtime("flash_player"),
+            Ok(PlayerRuntime::FlashPlayer)
+        ));
+    }
+
+    #[test]
+    fn choice_error_lists_variants() {
+        assert_eq!(
+            parse_letterbox("sideways").unwrap_err(),
+            "expected one of: off, fullscreen, on"
+        );
+        assert_eq!(
+            parse_open_url_mode("").unwrap_err(),
+            "expected one of: allow, confirm, deny"
+        );
+        assert_eq!(
+            parse_scale("stretch").unwrap_err(),
+            "expected one of: exact-fit, no-border, no-scale, show-all"
+        );
+    }
+
+    #[test]
+    fn desktop_command_line() {
+        let args = parse(&[
+            "--scale",
+            "show-all",
+            "--quality",
+            "high16x16-linear",
+            "--player-runtime",
+            "flash-player",
+            "--tcp-connections",
+            "ask",
+            "--socket-allow",
+            "example.com:843",
+        ])
+        .unwrap();
+        assert!(matches!(args.scale, Some(StageScaleMode::ShowAll)));
+        assert!(matches!(args.quality, Some(StageQuality::High16x16Linear)));
+        assert!(matches!(
+            args.player_runtime,
+            Some(PlayerRuntime::FlashPlayer)
+        ));
+        assert!(matches!(args.socket_mode, Some(SocketMode::Ask)));
+        assert_eq!(args.socket_allow, ["example.com:843"]);
+
+        // The previous name of `--tcp-connections`
+        let args = parse(&["--socket-mode", "deny"]).unwrap();
+        assert!(matches!(args.socket_mode, Some(SocketMode::Deny)));
+    }
+
+    #[test]
+    fn align() {
+        assert_eq!(parse_align(""), Ok(StageAlign::empty()));
+        assert_eq!(parse_align("T"), Ok(StageAlign::TOP));
+        assert_eq!(
+            parse_align("bR"),
+            Ok(StageAlign::BOTTOM | StageAlign::RIGHT)
+        );
+        assert_eq!(parse_align("TLTL"), Ok(StageAlign::TOP | StageAlign::LEFT));
+        assert_eq!(
+            parse_align("TX"),
+            Err("unknown alignment 'X', expected T, B, L or R".to_string())
+        );
+    }
+
+    #[test]
+    fn parameter() {
+        assert_eq!(parse_parameter("a=b"), Ok(("a".into(), "b".into())));
+        // Only the first `=` separates the name
+        assert_eq!(parse_parameter("a=b=c"), Ok(("a".into(), "b=c".into())));
+        assert_eq!(parse_parameter("a="), Ok(("a".into(), "".into())));
+        assert_eq!(parse_parameter("=b"), Err("expected NAME=VALUE".into()));
+        assert_eq!(parse_parameter("a"), Err("expected NAME=VALUE".into()));
+    }
+
+    #[test]
+    fn socket_address() {
+        assert_eq!(
+            parse_socket_address("example.com:843"),
+            Ok("example.com:843".into())
+        );
+        assert_eq!(parse_socket_address("[::1]:80"), Ok("[::1]:80".into()));
+        for invalid in [
+            "example.com",
+            ":80",
+            "example.com:",
+            "example.com:65536",
+            "a:b",
+        ] {
+            assert_eq!(
+                parse_socket_address(invalid),
+                Err("expected HOST:PORT".into()),
+                "{invalid}"
+            );
+        }
+    }
+
+    #[test]
+    fn bool_flags() {
+        let args = parse(&[]).unwrap();
+        assert_eq!(args.force_scale, None);
+        assert_eq!(args.upgrade_to_https, None);
+
+        let args = parse(&["--force-scale", "--force-align=true"]).unwrap();
+        assert_eq!(args.force_scale, Some(true));
+        assert_eq!(args.force_align, Some(true));
+
+        let args = parse(&["--upgrade-to-https=false", "--dummy-external-interface"]).unwrap();
+        assert_eq!(args.upgrade_to_https, Some(false));
+        assert_eq!(args.dummy_external_interface, Some(true));
+
+        assert!(parse(&["--force-scale=maybe"]).is_err());
+    }
+
+    #[test]
+    fn apply_overrides_bundle() {
+        let url = Url::parse("https://example.com/movie.swf").unwrap();
+        let mut launch = Launch::remote(url, vec![("a".into(), "1".into())]);
+        launch.info.player.force_scale = Some(true);
+        launch.info.player.upgrade_to_https = Some(true);
+
+        let args = parse(&["--force-scale=false", "-P", "a=2", "-P", "b=3"]).unwrap();
+        args.apply(&mut launch);
+        let options = &launch.info.player;
+        assert_eq!(options.force_scale, Some(false));
+        // Not given, so left as it was
+        assert_eq!(options.upgrade_to_https, Some(true));
+        assert_eq!(
+            options.parameters,
+            [
+                ("a".to_string(), "2".to_string()),
+                ("b".to_string(), "3".to_string())
+            ]
+        );
+    }
+}
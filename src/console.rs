@@ -0,0 +1,230 @@
+//! Collecting the movie's `trace()` output and AVM warnings, to show them in
+//! the app.
+//!
+//! Each player session gets its own buffer, so that the output isn't mixed
+//! with the rest of the app's logs.
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fmt;
+use std::rc::Rc;
+
+use ruffle_core::backend::log::LogBackend;
+
+/// The number of lines kept per session, older lines are dropped.
+pub const CONSOLE_CAPACITY: usize = 5000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConsoleLevel {
+    Trace,
+    Warning,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConsoleEntry {
+    pub level: ConsoleLevel,
+    pub message: String,
+}
+
+impl fmt::Display for ConsoleEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.level {
+            ConsoleLevel::Trace => write!(f, "{}", self.message),
+            ConsoleLevel::Warning => write!(f, "warning: {}", self.message),
+        }
+    }
+}
+
+/// A ring buffer of the most recent console output.
+pub struct ConsoleBuffer {
+    entries: VecDeque<ConsoleEntry>,
+    capacity: usize,
+    /// The number of entries dropped to make room for newer ones.
+    dropped: u64,
+    /// Called after entries are added, e.g. to update a visible console.
+    listener: Option<Rc<dyn Fn()>>,
+}
+
+impl fmt::Debug for ConsoleBuffer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConsoleBuffer")
+            .field("entries", &self.entries.len())
+            .field("capacity", &self.capacity)
+            .field("dropped", &self.dropped)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for ConsoleBuffer {
+    fn default() -> Self {
+        Self::new(CONSOLE_CAPACITY)
+    }
+}
+
+impl ConsoleBuffer {
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "console must hold at least one entry");
+        Self {
+            entries: VecDeque::new(),
+            capacity,
+            dropped: 0,
+            listener: None,
+        }
+    }
+
+    pub fn push(&mut self, entry: ConsoleEntry) {
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+            self.dropped += 1;
+        }
+        self.entries.push_back(entry);
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.dropped = 0;
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &ConsoleEntry> {
+        self.entries.iter()
+    }
+
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    pub fn set_listener(&mut self, listener: Option<Rc<dyn Fn()>>) {
+        self.listener = listener;
+    }
+
+    /// The entries containing `filter`, ignoring case, one per line.
+    pub fn text(&self, filter: &str) -> String {
+        let filter = filter.to_lowercase();
+        let mut text = String::new();
+        if self.dropped > 0 {
+            text.push_str(&format!("({} earlier lines dropped)\n", self.dropped));
+        }
+        for entry in &self.entries {
+            let line = entry.to_string();
+            if filter.is_empty() || line.to_lowercase().contains(&filter) {
+                text.push_str(&line);
+                text.push('\n');
+            }
+        }
+        text
+    }
+}
+
+/// Writes the player's output to a [`ConsoleBuffer`].
+pub struct ConsoleLogBackend(Rc<RefCell<ConsoleBuffer>>);
+
+impl ConsoleLogBackend {
+    pub fn new(buffer: Rc<RefCell<ConsoleBuffer>>) -> Self {
+        Self(buffer)
+    }
+
+    fn push(&self, level: ConsoleLevel, message: &str) {
+        // The listener is called without the buffer borrowed, so that it
+        // may read it.
+        let listener = {
+            let mut buffer = self.0.borrow_mut();
+            buffer.push(ConsoleEntry {
+                level,
+                message: message.to_string(),
+            });
+            buffer.listener.clone()
+        };
+        if let Some(listener) = listener {
+            listener();
+        }
+    }
+}
+
+impl LogBackend for ConsoleLogBackend {
+    fn avm_trace(&self, message: &str) {
+        self.push(ConsoleLevel::Trace, message);
+    }
+
+    fn avm_warning(&self, message: &str) {
+        self.push(ConsoleLevel::Warning, message);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trace(message: &str) -> ConsoleEntry {
+        ConsoleEntry {
+            level: ConsoleLevel::Trace,
+            message: message.to_string(),
+        }
+    }
+
+    fn warning(message: &str) -> ConsoleEntry {
+        ConsoleEntry {
+            level: ConsoleLevel::Warning,
+            message: message.to_string(),
+        }
+    }
+
+    fn messages(buffer: &ConsoleBuffer) -> Vec<&str> {
+        buffer
+            .entries()
+            .map(|entry| entry.message.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn push() {
+        let mut buffer = ConsoleBuffer::new(3);
+        buffer.push(trace("a"));
+        buffer.push(warning("b"));
+        assert_eq!(messages(&buffer), ["a", "b"]);
+        assert_eq!(buffer.dropped(), 0);
+        assert_eq!(buffer.text(""), "a\nwarning: b\n");
+    }
+
+    #[test]
+    fn evict() {
+        let mut buffer = ConsoleBuffer::new(2);
+        for message in ["a", "b", "c", "d"] {
+            buffer.push(trace(message));
+        }
+        assert_eq!(messages(&buffer), ["c", "d"]);
+        assert_eq!(buffer.dropped(), 2);
+        assert_eq!(buffer.text(""), "(2 earlier lines dropped)\nc\nd\n");
+    }
+
+    #[test]
+    fn clear() {
+        let mut buffer = ConsoleBuffer::new(1);
+        buffer.push(trace("a"));
+        buffer.push(trace("b"));
+        buffer.clear();
+        assert_eq!(messages(&buffer), Vec::<&str>::new());
+        assert_eq!(buffer.dropped(), 0);
+        assert_eq!(buffer.text(""), "");
+    }
+
+    #[test]
+    fn filter() {
+        let mut buffer = ConsoleBuffer::new(10);
+        buffer.push(trace("Loaded level 1"));
+        buffer.push(warning("Missing font"));
+        buffer.push(trace("score: 100"));
+
+        // Ignoring case
+        assert_eq!(buffer.text("LEVEL"), "Loaded level 1\n");
+        // The warning prefix is part of the line
+        assert_eq!(buffer.text("warning"), "warning: Missing font\n");
+        assert_eq!(buffer.text("nothing"), "");
+    }
+
+    #[test]
+    fn filter_keeps_dropped_count() {
+        let mut buffer = ConsoleBuffer::new(1);
+        buffer.push(trace("a"));
+        buffer.push(trace("b"));
+        assert_eq!(buffer.text("x"), "(1 earlier lines dropped)\n");
+    }
+}
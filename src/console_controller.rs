@@ -0,0 +1,328 @@
+//! Showing a movie's console output, see [`crate::console`].
+use std::cell::{Cell, OnceCell, RefCell};
+use std::ptr;
+use std::rc::Rc;
+
+use block2::{Block, RcBlock};
+use objc2::rc::{Allocated, Retained, Weak};
+use objc2::runtime::{AnyObject, Bool};
+use objc2::{declare_class, msg_send, msg_send_id, mutability, sel, ClassType, DeclaredClass};
+use objc2_foundation::{
+    ns_string, CGPoint, CGRect, CGSize, MainThreadMarker, NSArray, NSBundle, NSCoder, NSError,
+    NSObjectProtocol, NSRange, NSString, NSURL,
+};
+use objc2_ui_kit::{
+    UIActivityType, UIActivityViewController, UIAlertAction, UIAlertActionStyle, UIAlertController,
+    UIAlertControllerStyle, UIBarButtonItem, UIBarButtonSystemItem, UIColor, UIControlEvents,
+    UIFont, UIFontWeightRegular, UIPasteboard, UITextBorderStyle, UITextField, UITextFieldViewMode,
+    UITextView, UIViewController,
+};
+
+use crate::actions::{action, bar_button_item, image_bar_button_item};
+use crate::console::ConsoleBuffer;
+
+/// How long to wait after new output before showing it, so that movies that
+/// trace a lot don't reload the text view every frame.
+const RELOAD_DELAY: f64 = 0.25;
+
+#[derive(Default)]
+pub struct Ivars {
+    /// The name of the movie, used when exporting.
+    name: OnceCell<String>,
+    buffer: OnceCell<Rc<RefCell<ConsoleBuffer>>>,
+    text_view: OnceCell<Retained<UITextView>>,
+    filter_field: OnceCell<Retained<UITextField>>,
+    share_item: OnceCell<Retained<UIBarButtonItem>>,
+    /// Whether a reload is already scheduled.
+    reload_scheduled: Cell<bool>,
+}
+
+declare_class!(
+    #[derive(Debug)]
+    pub struct ConsoleController;
+
+    unsafe impl ClassType for ConsoleController {
+        type Super = UIViewController;
+        type Mutability = mutability::MainThreadOnly;
+        const NAME: &'static str = "ConsoleController";
+    }
+
+    impl DeclaredClass for ConsoleController {
+        type Ivars = Ivars;
+    }
+
+    unsafe impl NSObjectProtocol for ConsoleController {}
+
+    unsafe impl ConsoleController {
+        #[method_id(initWithNibName:bundle:)]
+        fn _init_with_nib_name_bundle(
+            this: Allocated<Self>,
+            nib_name_or_nil: Option<&NSString>,
+            nib_bundle_or_nil: Option<&NSBundle>,
+        ) -> Retained<Self> {
+            let this = this.set_ivars(Ivars::default());
+            unsafe { msg_send_id![super(this), initWithNibName: nib_name_or_nil, bundle: nib_bundle_or_nil] }
+        }
+
+        #[method_id(initWithCoder:)]
+        fn _init_with_coder(
+            this: Allocated<Self>,
+            coder: &NSCoder,
+        ) -> Option<Retained<Self>> {
+            let this = this.set_ivars(Ivars::default());
+            unsafe { msg_send_id![super(this), initWithCoder: coder] }
+        }
+
+        #[method(loadView)]
+        fn _load_view(&self) {
+            self.load_view();
+        }
+
+        #[method(viewWillAppear:)]
+        fn _view_will_appear(&self, animated: bool) {
+            let _: () = unsafe { msg_send![super(self), viewWillAppear: animated] };
+            self.view_will_appear();
+        }
+
+        #[method(viewDidDisappear:)]
+        fn _view_did_disappear(&self, animated: bool) {
+            self.view_did_disappear();
+            let _: () = unsafe { msg_send![super(self), viewDidDisappear: animated] };
+        }
+
+        #[method(reloadConsole)]
+        fn _reload_console(&self) {
+            self.ivars().reload_scheduled.set(false);
+            self.reload();
+        }
+    }
+);
+
+impl ConsoleController {
+    pub fn new(
+        mtm: MainThreadMarker,
+        name: String,
+        buffer: Rc<RefCell<ConsoleBuffer>>,
+    ) -> Retained<Self> {
+        let this = mtm.alloc().set_ivars(Ivars {
+            name: OnceCell::from(name),
+            buffer: OnceCell::from(buffer),
+            ..Default::default()
+        });
+        let nil = ptr::null::<AnyObject>();
+        unsafe { msg_send_id![super(this), initWithNibName: nil, bundle: nil] }
+    }
+
+    fn buffer(&self) -> &RefCell<ConsoleBuffer> {
+        self.ivars().buffer.get().expect("created with a buffer")
+    }
+
+    fn text_view(&self) -> &UITextView {
+        self.ivars().text_view.get().expect("view loaded")
+    }
+
+    fn filter(&self) -> String {
+        let field = self.ivars().filter_field.get().expect("view loaded");
+        unsafe { field.text() }
+            .map(|text| text.to_string())
+            .unwrap_or_default()
+    }
+
+    fn load_view(&self) {
+        let mtm = MainThreadMarker::from(self);
+        let frame = CGRect::new(CGPoint::ZERO, CGSize::new(1.0, 1.0));
+
+        unsafe {
+            let text_view = UITextView::initWithFrame_textContainer(mtm.alloc(), frame, None);
+            text_view.setEditable(false);
+            text_view.setFont(Some(&UIFont::monospacedSystemFontOfSize_weight(
+                12.0,
+                UIFontWeightRegular,
+            )));
+            text_view.setBackgroundColor(Some(&UIColor::systemBackgroundColor()));
+            text_view.setAlwaysBounceVertical(true);
+            self.setView(Some(&text_view));
+            self.ivars()
+                .text_view
+                .set(text_view)
+                .unwrap_or_else(|_| panic!("loadView once"));
+
+            let filter_field = UITextField::initWithFrame(
+                mtm.alloc(),
+                CGRect::new(CGPoint::ZERO, CGSize::new(240.0, 32.0)),
+            );
+            filter_field.setPlaceholder(Some(ns_string!("Filter")));
+            filter_field.setBorderStyle(UITextBorderStyle::RoundedRect);
+            filter_field.setClearButtonMode(UITextFieldViewMode::WhileEditing);
+            let this = Weak::new(self);
+            let filter_action = action(mtm, move || {
+                if let Some(this) = this.load() {
+                    this.reload();
+                }
+            });
+            filter_field
+                .addAction_forControlEvents(&filter_action, UIControlEvents::EditingChanged);
+            self.navigationItem().setTitleView(Some(&filter_field));
+            self.ivars()
+                .filter_field
+                .set(filter_field)
+                .unwrap_or_else(|_| panic!("loadView once"));
+        }
+
+        let this = Weak::new(self);
+        let done = bar_button_item(UIBarButtonSystemItem::Done, mtm, move || {
+            if let Some(this) = this.load() {
+                unsafe { this.dismissViewControllerAnimated_completion(true, None) };
+            }
+        });
+
+        let this = Weak::new(self);
+        let share = bar_button_item(UIBarButtonSystemItem::Action, mtm, move || {
+            if let Some(this) = this.load() {
+                this.export();
+            }
+        });
+        let this = Weak::new(self);
+        let copy = image_bar_button_item(ns_string!("doc.on.doc"), mtm, move || {
+            if let Some(this) = this.load() {
+                this.copy_text();
+            }
+        });
+        let this = Weak::new(self);
+        let clear = bar_button_item(UIBarButtonSystemItem::Trash, mtm, move || {
+            if let Some(this) = this.load() {
+                this.buffer().borrow_mut().clear();
+                this.reload();
+            }
+        });
+
+        unsafe {
+            let navigation_item = self.navigationItem();
+            navigation_item.setLeftBarButtonItem(Some(&done));
+            // The first item is shown rightmost
+            let items = NSArray::from_vec(vec![share.retain(), copy, clear]);
+            navigation_item.setRightBarButtonItems(Some(&items));
+        }
+        self.ivars()
+            .share_item
+            .set(share)
+            .unwrap_or_else(|_| panic!("loadView once"));
+    }
+
+    /// Show new output while visible.
+    fn view_will_appear(&self) {
+        let this = Weak::new(self);
+        let listener = Rc::new(move || {
+            if let Some(this) = this.load() {
+                this.schedule_reload();
+            }
+        });
+        self.buffer().borrow_mut().set_listener(Some(listener));
+        self.reload();
+    }
+
+    fn view_did_disappear(&self) {
+        self.buffer().borrow_mut().set_listener(None);
+    }
+
+    fn schedule_reload(&self) {
+        if self.ivars().reload_scheduled.replace(true) {
+            return;
+        }
+        let nil = ptr::null::<AnyObject>();
+        let _: () = unsafe {
+            msg_send![
+                self,
+                performSelector: sel!(reloadConsole),
+                withObject: nil,
+                afterDelay: RELOAD_DELAY,
+            ]
+        };
+    }
+
+    /// Show the output matching the filter, scrolled to the newest line.
+    fn reload(&self) {
+        let text = NSString::from_str(&self.buffer().borrow().text(&self.filter()));
+        let text_view = self.text_view();
+        unsafe {
+            text_view.setText(Some(&text));
+            text_view.scrollRangeToVisible(NSRange::new(text.length(), 0));
+        }
+    }
+
+    fn copy_text(&self) {
+        let mtm = MainThreadMarker::from(self);
+        let text = self.buffer().borrow().text(&self.filter());
+        unsafe { UIPasteboard::generalPasteboard(mtm).setString(Some(&NSString::from_str(&text))) };
+    }
+
+    /// Write the output matching the filter to a temporary file, and let the
+    /// user share or save it.
+    fn export(&self) {
+        let mtm = MainThreadMarker::from(self);
+        let name = self.ivars().name.get().expect("created with a name");
+        // The name is user-provided, so make it safe to use as a file name
+        let file_name: String = name
+            .chars()
+            .map(|c| {
+                if matches!(c, '/' | '\\' | ':') {
+                    '_'
+                } else {
+                    c
+                }
+            })
+            .collect();
+        let path = std::env::temp_dir().join(format!("{file_name} Console.txt"));
+        let text = self.buffer().borrow().text(&self.filter());
+        if let Err(err) = std::fs::write(&path, text) {
+            tracing::error!(?path, "failed writing console output: {err}");
+            self.show_error(&format!("Could not export the console:\n{err}"));
+            return;
+        }
+
+        let url = unsafe { NSURL::fileURLWithPath(&NSString::from_str(&path.to_string_lossy())) };
+        let items = NSArray::from_vec(vec![Retained::into_super(Retained::into_super(url))]);
+        unsafe {
+            let activity = UIActivityViewController::initWithActivityItems_applicationActivities(
+                mtm.alloc(),
+                &items,
+                None,
+            );
+            // Shown as a popover on iPad
+            if let Some(popover) = activity.popoverPresentationController() {
+                popover.setBarButtonItem(self.ivars().share_item.get().map(|item| &**item));
+            }
+            // Called once the sheet is dismissed, whether or not the file
+            // was shared, and the receiver has its own copy by then.
+            let completion = RcBlock::new(
+                move |_: *mut UIActivityType, _: Bool, _: *mut NSArray, _: *mut NSError| {
+                    if let Err(err) = std::fs::remove_file(&path) {
+                        tracing::warn!(?path, "failed removing exported console: {err}");
+                    }
+                },
+            );
+            let completion_ptr: *const Block<_> = &*completion;
+            activity.setCompletionWithItemsHandler(completion_ptr.cast_mut());
+            self.presentViewController_animated_completion(&activity, true, None);
+        }
+    }
+
+    fn show_error(&self, message: &str) {
+        let mtm = MainThreadMarker::from(self);
+        unsafe {
+            let alert = UIAlertController::alertControllerWithTitle_message_preferredStyle(
+                Some(ns_string!("Export Failed")),
+                Some(&NSString::from_str(message)),
+                UIAlertControllerStyle::Alert,
+                mtm,
+            );
+            alert.addAction(&UIAlertAction::actionWithTitle_style_handler(
+                Some(ns_string!("OK")),
+                UIAlertActionStyle::Default,
+                ptr::null_mut(),
+                mtm,
+            ));
+            self.presentViewController_animated_completion(&alert, true, None);
+        }
+    }
+}
@@ -0,0 +1,247 @@
+//! Parsing `ruffle://` links.
+//!
+//! Supported links:
+//! - `ruffle://library/<uuid>` opens an item in the library.
+//! - `ruffle://open?url=<url>&param=<name>=<value>` opens a remote SWF
+//!   without importing it. `param` may be repeated, and is passed to the
+//!   movie as FlashVars.
+//!
+//! This intentionally doesn't depend on UIKit.
+use url::Url;
+use uuid::Uuid;
+
+pub const SCHEME: &str = "ruffle";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeepLink {
+    LibraryItem(Uuid),
+    Open {
+        url: Url,
+        parameters: Vec<(String, String)>,
+    },
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum DeepLinkError {
+    #[error("invalid URL: {0}")]
+    InvalidUrl(#[from] url::ParseError),
+    #[error("not a {SCHEME}:// link")]
+    WrongScheme,
+    #[error("unknown link type {0:?}")]
+    UnknownAction(String),
+    #[error("invalid library item id {0:?}")]
+    InvalidId(String),
+    #[error("unexpected path {0:?}")]
+    UnexpectedPath(String),
+    #[error("unexpected query parameter {0:?}")]
+    UnexpectedQuery(String),
+    #[error("missing movie URL")]
+    MissingMovieUrl,
+    #[error("movie URL given more than once")]
+    DuplicateMovieUrl,
+    #[error("movie URL must use http or https, not {0:?}")]
+    UnsupportedMovieScheme(String),
+    #[error("parameter must be of the form `name=value`: {0:?}")]
+    InvalidParameter(String),
+}
+
+pub fn parse_deep_link(input: &str) -> Result<DeepLink, DeepLinkError> {
+    let link = Url::parse(input)?;
+    if link.scheme() != SCHEME {
+        return Err(DeepLinkError::WrongScheme);
+    }
+
+    match link.host_str().unwrap_or_default() {
+        "library" => {
+            if let Some((key, _)) = link.query_pairs().next() {
+                return Err(DeepLinkError::UnexpectedQuery(key.into_owned()));
+            }
+            let path = link.path().strip_prefix('/').unwrap_or_default();
+            // Allow a trailing slash, but nothing else after the id
+            let id = path.strip_suffix('/').unwrap_or(path);
+            Uuid::try_parse(id)
+                .map(DeepLink::LibraryItem)
+                .map_err(|_| DeepLinkError::InvalidId(id.to_string()))
+        }
+        "open" => {
+            if !matches!(link.path(), "" | "/") {
+                return Err(DeepLinkError::UnexpectedPath(link.path().to_string()));
+            }
+
+            let mut url = None;
+            let mut parameters = vec![];
+            for (key, value) in link.query_pairs() {
+                match &*key {
+                    "url" => {
+                        if url.is_some() {
+                            return Err(DeepLinkError::DuplicateMovieUrl);
+                        }
+                        url = Some(Url::parse(&value)?);
+                    }
+                    "param" => match value.split_once('=') {
+                        Some((name, value)) if !name.is_empty() => {
+                            parameters.push((name.to_string(), value.to_string()));
+                        }
+                        _ => return Err(DeepLinkError::InvalidParameter(value.into_owned())),
+                    },
+                    _ => return Err(DeepLinkError::UnexpectedQuery(key.into_owned())),
+                }
+            }
+
+            let url = url.ok_or(DeepLinkError::MissingMovieUrl)?;
+            // Local files must be opened through the Files app instead, so
+            // that links can't be used to read arbitrary files.
+            if !matches!(url.scheme(), "http" | "https") {
+                return Err(DeepLinkError::UnsupportedMovieScheme(
+                    url.scheme().to_string(),
+                ));
+            }
+            Ok(DeepLink::Open { url, parameters })
+        }
+        action => Err(DeepLinkError::UnknownAction(action.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ID: &str = "67e55044-10b1-426f-9247-bb680e5fe0c8";
+
+    fn open(url: &str, parameters: &[(&str, &str)]) -> DeepLink {
+        DeepLink::Open {
+            url: Url::parse(url).unwrap(),
+            parameters: parameters
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn library_item() {
+        let id = Uuid::parse_str(ID).unwrap();
+        let link = parse_deep_link(&format!("ruffle://library/{ID}")).unwrap();
+        assert_eq!(link, DeepLink::LibraryItem(id));
+        let link = parse_deep_link(&format!("ruffle://library/{ID}/")).unwrap();
+        assert_eq!(link, DeepLink::LibraryItem(id));
+    }
+
+    #[test]
+    fn library_item_invalid() {
+        assert!(matches!(
+            parse_deep_link("ruffle://library/not-a-uuid"),
+            Err(DeepLinkError::InvalidId(id)) if id == "not-a-uuid"
+        ));
+        assert!(matches!(
+            parse_deep_link("ruffle://library/"),
+            Err(DeepLinkError::InvalidId(_))
+        ));
+        // Stray path segments
+        assert!(matches!(
+            parse_deep_link(&format!("ruffle://library/{ID}/extra")),
+            Err(DeepLinkError::InvalidId(_))
+        ));
+        assert!(matches!(
+            parse_deep_link(&format!("ruffle://library/{ID}?url=x")),
+            Err(DeepLinkError::UnexpectedQuery(key)) if key == "url"
+        ));
+    }
+
+    #[test]
+    fn open_url() {
+        let link = parse_deep_link("ruffle://open?url=https%3A%2F%2Fexample.com%2Fgame.swf");
+        assert_eq!(link.unwrap(), open("https://example.com/game.swf", &[]));
+        let link = parse_deep_link("ruffle://open/?url=http://example.com/game.swf");
+        assert_eq!(link.unwrap(), open("http://example.com/game.swf", &[]));
+    }
+
+    #[test]
+    fn open_url_with_parameters() {
+        let link = parse_deep_link(
+            "ruffle://open?url=https://example.com/game.swf&param=level%3D2&param=name=a%3Db&param=empty=",
+        );
+        assert_eq!(
+            link.unwrap(),
+            open(
+                "https://example.com/game.swf",
+                &[("level", "2"), ("name", "a=b"), ("empty", "")],
+            )
+        );
+    }
+
+    #[test]
+    fn open_invalid() {
+        assert!(matches!(
+            parse_deep_link("ruffle://open"),
+            Err(DeepLinkError::MissingMovieUrl)
+        ));
+        assert!(matches!(
+            parse_deep_link("ruffle://open?param=a=b"),
+            Err(DeepLinkError::MissingMovieUrl)
+        ));
+        assert!(matches!(
+            parse_deep_link("ruffle://open?url=https://a.com/a.swf&url=https://b.com/b.swf"),
+            Err(DeepLinkError::DuplicateMovieUrl)
+        ));
+        assert!(matches!(
+            parse_deep_link("ruffle://open?url=not%20a%20url"),
+            Err(DeepLinkError::InvalidUrl(_))
+        ));
+        assert!(matches!(
+            parse_deep_link("ruffle://open?url=https://example.com/game.swf&param=novalue"),
+            Err(DeepLinkError::InvalidParameter(param)) if param == "novalue"
+        ));
+        assert!(matches!(
+            parse_deep_link("ruffle://open?url=https://example.com/game.swf&param==value"),
+            Err(DeepLinkError::InvalidParameter(_))
+        ));
+        assert!(matches!(
+            parse_deep_link("ruffle://open?url=https://example.com/game.swf&foo=bar"),
+            Err(DeepLinkError::UnexpectedQuery(key)) if key == "foo"
+        ));
+        // Stray path segments
+        assert!(matches!(
+            parse_deep_link("ruffle://open/extra?url=https://example.com/game.swf"),
+            Err(DeepLinkError::UnexpectedPath(path)) if path == "/extra"
+        ));
+    }
+
+    #[test]
+    fn open_non_http() {
+        for url in [
+            "file:///etc/passwd",
+            "ftp://example.com/game.swf",
+            "ruffle://open",
+        ] {
+            let link = format!("ruffle://open?url={url}");
+            assert!(
+                matches!(
+                    parse_deep_link(&link),
+                    Err(DeepLinkError::UnsupportedMovieScheme(_))
+                ),
+                "{link}"
+            );
+        }
+    }
+
+    #[test]
+    fn wrong_scheme_or_host() {
+        assert!(matches!(
+            parse_deep_link(&format!("https://library/{ID}")),
+            Err(DeepLinkError::WrongScheme)
+        ));
+        assert!(matches!(
+            parse_deep_link("ruffle://play?url=https://example.com/game.swf"),
+            Err(DeepLinkError::UnknownAction(action)) if action == "play"
+        ));
+        assert!(matches!(
+            parse_deep_link("ruffle:library"),
+            Err(DeepLinkError::UnknownAction(action)) if action.is_empty()
+        ));
+        assert!(matches!(
+            parse_deep_link("not a link"),
+            Err(DeepLinkError::InvalidUrl(_))
+        ));
+    }
+}
@@ -1,21 +1,36 @@
 use std::cell::{Cell, OnceCell, RefCell};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ptr::{self, NonNull};
+use std::str::FromStr;
+use std::time::Duration;
 
 use block2::{Block, RcBlock};
-use objc2::rc::{Allocated, Retained};
+use objc2::rc::{Allocated, Retained, Weak};
+use objc2::runtime::ProtocolObject;
 use objc2::{declare_class, msg_send, msg_send_id, mutability, ClassType, DeclaredClass};
 use objc2_foundation::{
-    ns_string, MainThreadMarker, NSArray, NSBundle, NSCoder, NSIndexPath, NSInteger,
+    ns_string, MainThreadMarker, NSArray, NSBundle, NSCoder, NSIndexPath, NSInteger, NSObject,
     NSObjectProtocol, NSString,
 };
 use objc2_ui_kit::{
-    NSIndexPathUIKitAdditions, UIAction, UIButton, UILabel, UIMenu, UIMenuElementState,
-    UIMenuOptions, UINavigationItem, UIScrollViewDelegate, UISegmentedControl, UITableView,
-    UITableViewCell, UITableViewDataSource, UITableViewDelegate, UITextField, UIViewController,
+    NSIndexPathUIKitAdditions, UIAction, UIAlertAction, UIAlertActionStyle, UIAlertController,
+    UIAlertControllerStyle, UIButton, UIColor, UIControl, UIControlEvents, UILabel,
+    UIListContentConfiguration, UIMenu, UIMenuElementState, UIMenuOptions, UINavigationItem,
+    UIScrollViewDelegate, UISegmentedControl, UITableView, UITableViewCell,
+    UITableViewCellEditingStyle, UITableViewCellSelectionStyle, UITableViewCellStyle,
+    UITableViewDataSource, UITableViewDelegate, UITableViewRowAnimation, UITextField,
+    UIViewController,
 };
+use ruffle_core::backend::navigator::{OpenURLMode, SocketMode};
 use ruffle_core::{LoadBehavior, PlayerRuntime, StageAlign, StageScaleMode};
 use ruffle_frontend_utils::bundle::info::BundleInformation;
 use ruffle_frontend_utils::player_options::PlayerOptions;
 use ruffle_render::quality::StageQuality;
+use url::{ParseError, Url};
+
+use crate::actions::action;
+use crate::settings::ItemSettings;
 
 #[derive(Clone, Copy, Debug)]
 enum FormElement {
@@ -24,19 +39,55 @@ enum FormElement {
     String {
         label: &'static str,
         text: fn(&PlayerOptions) -> Option<String>,
+        /// Empty text means that the default should be used.
+        set_text: fn(&mut PlayerOptions, &str) -> Result<(), String>,
     },
     Select {
         label: &'static str,
         variants: &'static [&'static str],
         enabled_variant: fn(&PlayerOptions) -> Option<&'static str>,
+        /// `None` means that the default should be used.
+        set_variant: fn(&mut PlayerOptions, Option<&str>),
+    },
+    /// Like `Select`, but for the settings that only this app knows about.
+    SettingSelect {
+        label: &'static str,
+        variants: &'static [&'static str],
+        enabled_variant: fn(&ItemSettings) -> Option<&'static str>,
+        /// `None` means that the default should be used.
+        set_variant: fn(&mut ItemSettings, Option<&str>),
     },
     Bool {
         label: &'static str,
         value: fn(&PlayerOptions) -> Option<bool>,
+        set_value: fn(&mut PlayerOptions, Option<bool>),
     },
 }
 
+/// Parse a text field, treating empty text as the default.
+fn parse_optional<T: FromStr>(text: &str) -> Result<Option<T>, String>
+where
+    T::Err: fmt::Display,
+{
+    let text = text.trim();
+    if text.is_empty() {
+        return Ok(None);
+    }
+    text.parse()
+        .map(Some)
+        .map_err(|err: T::Err| err.to_string())
+}
+
+/// The URL of new items, until the user enters where the movie is.
+pub fn placeholder_url() -> Url {
+    Url::parse("file://").unwrap()
+}
+
 // TODO: Localization
+const NAME_LABEL: &str = "Name";
+const SOURCE_LABEL: &str = "URL";
+const PARAMETER_LABEL: &str = "Movie parameter";
+
 const FORM: &[&[FormElement]] = &[
     // Required
     &[FormElement::Name, FormElement::Source],
@@ -49,6 +100,11 @@ const FORM: &[&[FormElement]] = &[
                     .max_execution_duration
                     .map(|duration| duration.as_secs().to_string())
             },
+            set_text: |options, text| {
+                options.max_execution_duration =
+                    parse_optional::<u64>(text)?.map(Duration::from_secs);
+                Ok(())
+            },
         },
         FormElement::Select {
             label: "Quality",
@@ -74,10 +130,27 @@ const FORM: &[&[FormElement]] = &[
                     StageQuality::High16x16Linear => "High (16x16) Linear",
                 })
             },
+            set_variant: |options, variant| {
+                options.quality = variant.map(|variant| match variant {
+                    "Low" => StageQuality::Low,
+                    "Medium" => StageQuality::Medium,
+                    "High" => StageQuality::High,
+                    "Best" => StageQuality::Best,
+                    "High (8x8)" => StageQuality::High8x8,
+                    "High (8x8) Linear" => StageQuality::High8x8Linear,
+                    "High (16x16)" => StageQuality::High16x16,
+                    "High (16x16) Linear" => StageQuality::High16x16Linear,
+                    _ => unreachable!("unknown quality {variant:?}"),
+                });
+            },
         },
         FormElement::String {
             label: "Player version",
             text: |options| options.player_version.map(|version| version.to_string()),
+            set_text: |options, text| {
+                options.player_version = parse_optional(text)?;
+                Ok(())
+            },
         },
         FormElement::Select {
             label: "Player runtime",
@@ -88,10 +161,25 @@ const FORM: &[&[FormElement]] = &[
                     PlayerRuntime::AIR => "Adobe AIR",
                 })
             },
+            set_variant: |options, variant| {
+                options.player_runtime = variant.map(|variant| match variant {
+                    "Flash Player" => PlayerRuntime::FlashPlayer,
+                    "Adobe AIR" => PlayerRuntime::AIR,
+                    _ => unreachable!("unknown runtime {variant:?}"),
+                });
+            },
         },
         FormElement::String {
             label: "Custom framerate (fps)",
             text: |options| options.frame_rate.map(|rate: f64| rate.to_string()),
+            set_text: |options, text| {
+                let frame_rate = parse_optional::<f64>(text)?;
+                if frame_rate.is_some_and(|rate| !rate.is_finite() || rate <= 0.0) {
+                    return Err("frame rate must be a positive number".into());
+                }
+                options.frame_rate = frame_rate;
+                Ok(())
+            },
         },
     ],
     // Stage Alignment
@@ -129,10 +217,25 @@ const FORM: &[&[FormElement]] = &[
                     _ => "Center",
                 })
             },
+            set_variant: |options, variant| {
+                options.align = variant.map(|variant| match variant {
+                    "Center" => StageAlign::empty(),
+                    "Top" => StageAlign::TOP,
+                    "Bottom" => StageAlign::BOTTOM,
+                    "Left" => StageAlign::LEFT,
+                    "Right" => StageAlign::RIGHT,
+                    "Top-Left" => StageAlign::TOP | StageAlign::LEFT,
+                    "Top-Right" => StageAlign::TOP | StageAlign::RIGHT,
+                    "Bottom-Left" => StageAlign::BOTTOM | StageAlign::LEFT,
+                    "Bottom-Right" => StageAlign::BOTTOM | StageAlign::RIGHT,
+                    _ => unreachable!("unknown alignment {variant:?}"),
+                });
+            },
         },
         FormElement::Bool {
             label: "Force",
             value: |options| options.force_align,
+            set_value: |options, value| options.force_align = value,
         },
     ],
     // Scale mode
@@ -140,15 +243,10 @@ const FORM: &[&[FormElement]] = &[
         FormElement::Select {
             label: "Scale mode",
             variants: &[
-                "Center",
-                "Top",
-                "Bottom",
-                "Left",
-                "Right",
-                "Top-Left",
-                "Top-Right",
-                "Bottom-Left",
-                "Bottom-Right",
+                "Unscaled (100%)",
+                "Zoom to Fit",
+                "Stretch to Fit",
+                "Crop to Fit",
             ],
             enabled_variant: |options| {
                 options.scale.map(|scale| match scale {
@@ -158,10 +256,20 @@ const FORM: &[&[FormElement]] = &[
                     StageScaleMode::NoBorder => "Crop to Fit",
                 })
             },
+            set_variant: |options, variant| {
+                options.scale = variant.map(|variant| match variant {
+                    "Unscaled (100%)" => StageScaleMode::NoScale,
+                    "Zoom to Fit" => StageScaleMode::ShowAll,
+                    "Stretch to Fit" => StageScaleMode::ExactFit,
+                    "Crop to Fit" => StageScaleMode::NoBorder,
+                    _ => unreachable!("unknown scale mode {variant:?}"),
+                });
+            },
         },
         FormElement::Bool {
             label: "Force",
             value: |options| options.force_scale,
+            set_value: |options, value| options.force_scale = value,
         },
     ],
     // Network settings
@@ -169,22 +277,39 @@ const FORM: &[&[FormElement]] = &[
         FormElement::String {
             label: "Custom base URL",
             text: |options| options.base.as_ref().map(|url| url.to_string()),
+            set_text: |options, text| {
+                options.base = parse_optional(text)?;
+                Ok(())
+            },
         },
         FormElement::String {
             label: "Spoof SWF URL",
             text: |options| options.spoof_url.as_ref().map(|url| url.to_string()),
+            set_text: |options, text| {
+                options.spoof_url = parse_optional(text)?;
+                Ok(())
+            },
         },
         FormElement::String {
             label: "Referer URL",
             text: |options| options.referer.as_ref().map(|url| url.to_string()),
+            set_text: |options, text| {
+                options.referer = parse_optional(text)?;
+                Ok(())
+            },
         },
         FormElement::String {
             label: "Cookie",
             text: |options| options.cookie.clone(),
+            set_text: |options, text| {
+                options.cookie = Some(text).filter(|text| !text.is_empty()).map(Into::into);
+                Ok(())
+            },
         },
         FormElement::Bool {
             label: "Upgrade HTTP to HTTPS",
             value: |options| options.upgrade_to_https,
+            set_value: |options, value| options.upgrade_to_https = value,
         },
         FormElement::Select {
             label: "Load behaviour",
@@ -196,15 +321,72 @@ const FORM: &[&[FormElement]] = &[
                     LoadBehavior::Blocking => "Blocking",
                 })
             },
+            set_variant: |options, variant| {
+                options.load_behavior = variant.map(|variant| match variant {
+                    "Streaming" => LoadBehavior::Streaming,
+                    "Delayed" => LoadBehavior::Delayed,
+                    "Blocking" => LoadBehavior::Blocking,
+                    _ => unreachable!("unknown load behaviour {variant:?}"),
+                });
+            },
         },
         FormElement::Bool {
             label: "Dummy external interface",
             value: |options| options.dummy_external_interface,
+            set_value: |options, value| options.dummy_external_interface = value,
+        },
+        FormElement::SettingSelect {
+            label: "Open websites",
+            variants: &["Allow", "Ask", "Deny"],
+            enabled_variant: |settings| {
+                settings.open_url_mode.map(|mode| match mode {
+                    OpenURLMode::Allow => "Allow",
+                    OpenURLMode::Confirm => "Ask",
+                    OpenURLMode::Deny => "Deny",
+                })
+            },
+            set_variant: |settings, variant| {
+                settings.open_url_mode = variant.map(|variant| match variant {
+                    "Allow" => OpenURLMode::Allow,
+                    "Ask" => OpenURLMode::Confirm,
+                    "Deny" => OpenURLMode::Deny,
+                    _ => unreachable!("unknown open URL mode {variant:?}"),
+                });
+            },
+        },
+        FormElement::SettingSelect {
+            label: "Network connections",
+            variants: &["Allow", "Ask", "Deny"],
+            enabled_variant: |settings| {
+                settings.socket_mode.map(|mode| match mode {
+                    SocketMode::Allow => "Allow",
+                    SocketMode::Ask => "Ask",
+                    SocketMode::Deny => "Deny",
+                })
+            },
+            set_variant: |settings, variant| {
+                settings.socket_mode = variant.map(|variant| match variant {
+                    "Allow" => SocketMode::Allow,
+                    "Ask" => SocketMode::Ask,
+                    "Deny" => SocketMode::Deny,
+                    _ => unreachable!("unknown socket mode {variant:?}"),
+                });
+            },
         },
     ],
-    // Movie parameters are placed at the end
+    // Movie parameters and remembered connections are placed at the end
 ];
 
+const PARAMETERS_SECTION: usize = FORM.len();
+const SOCKETS_SECTION: usize = FORM.len() + 1;
+
+/// The connections that the user has answered for, allowed ones first.
+fn remembered_sockets(settings: &ItemSettings) -> impl Iterator<Item = (&str, bool)> {
+    let allowed = settings.socket_allowed.iter().map(|a| (a.as_str(), true));
+    let denied = settings.socket_denied.iter().map(|a| (a.as_str(), false));
+    allowed.chain(denied)
+}
+
 // Roughly matches PlayerOptions
 
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
@@ -220,6 +402,12 @@ pub struct Ivars {
     table_view: OnceCell<Retained<UITableView>>,
     action: Cell<Action>,
     info: RefCell<Option<BundleInformation>>,
+    settings: RefCell<ItemSettings>,
+    /// The text of fields that couldn't be applied, by label.
+    ///
+    /// Kept so that the text is still shown if the cell is reused, and so
+    /// that we can refuse to save.
+    invalid: RefCell<BTreeMap<&'static str, String>>,
 }
 
 declare_class!(
@@ -279,6 +467,20 @@ declare_class!(
             // Docs say to call super
             let _: () = unsafe { msg_send![super(self), viewDidAppear: animated] };
         }
+
+        #[method(shouldPerformSegueWithIdentifier:sender:)]
+        fn _should_perform_segue(
+            &self,
+            identifier: Option<&NSString>,
+            _sender: Option<&NSObject>,
+        ) -> bool {
+            // Identifiers are set up in the Storyboard
+            if identifier.is_some_and(|identifier| identifier == ns_string!("save-item")) {
+                self.can_save()
+            } else {
+                true
+            }
+        }
     }
 
     // Storyboard
@@ -311,18 +513,57 @@ declare_class!(
             _table_view: &UITableView,
             section: NSInteger,
         ) -> NSInteger {
-            if FORM.len() == section as usize {
-                let info = self.ivars().info.borrow();
-                let options = &info.as_ref().expect("initialized").player;
-                options.parameters.len() as NSInteger + 1
-            } else {
-                FORM[section as usize].len() as NSInteger
+            match section as usize {
+                PARAMETERS_SECTION => {
+                    let info = self.ivars().info.borrow();
+                    let options = &info.as_ref().expect("initialized").player;
+                    options.parameters.len() as NSInteger + 1
+                }
+                SOCKETS_SECTION => {
+                    remembered_sockets(&self.ivars().settings.borrow()).count() as NSInteger
+                }
+                section => FORM[section].len() as NSInteger,
             }
         }
 
         #[method(numberOfSectionsInTableView:)]
         fn numberOfSectionsInTableView(&self, _table_view: &UITableView) -> NSInteger {
-            FORM.len() as NSInteger + 1
+            SOCKETS_SECTION as NSInteger + 1
+        }
+
+        #[method_id(tableView:titleForHeaderInSection:)]
+        fn tableView_titleForHeaderInSection(
+            &self,
+            _table_view: &UITableView,
+            section: NSInteger,
+        ) -> Option<Retained<NSString>> {
+            let settings = self.ivars().settings.borrow();
+            if section as usize == SOCKETS_SECTION && remembered_sockets(&settings).next().is_some() {
+                Some(NSString::from_str("Remembered connections"))
+            } else {
+                None
+            }
+        }
+
+        #[method(tableView:canEditRowAtIndexPath:)]
+        fn tableView_canEditRowAtIndexPath(
+            &self,
+            _table_view: &UITableView,
+            index_path: &NSIndexPath,
+        ) -> bool {
+            unsafe { index_path.section() } as usize == SOCKETS_SECTION
+        }
+
+        #[method(tableView:commitEditingStyle:forRowAtIndexPath:)]
+        fn tableView_commitEditingStyle_forRowAtIndexPath(
+            &self,
+            table_view: &UITableView,
+            editing_style: UITableViewCellEditingStyle,
+            index_path: &NSIndexPath,
+        ) {
+            if editing_style == UITableViewCellEditingStyle::Delete {
+                self.forget_socket(table_view, index_path);
+            }
         }
 
         #[method_id(tableView:cellForRowAtIndexPath:)]
@@ -337,13 +578,183 @@ declare_class!(
 
     unsafe impl UIScrollViewDelegate for EditController {}
 
-    unsafe impl UITableViewDelegate for EditController {}
+    #[allow(non_snake_case)]
+    unsafe impl UITableViewDelegate for EditController {
+        #[method(tableView:didSelectRowAtIndexPath:)]
+        fn tableView_didSelectRowAtIndexPath(
+            &self,
+            table_view: &UITableView,
+            index_path: &NSIndexPath,
+        ) {
+            self.did_select(table_view, index_path);
+        }
+    }
 );
 
 impl EditController {
-    pub fn configure(&self, action: Action, info: BundleInformation) {
+    pub fn configure(&self, action: Action, info: BundleInformation, settings: ItemSettings) {
         self.ivars().action.set(action);
         *self.ivars().info.borrow_mut() = Some(info);
+        *self.ivars().settings.borrow_mut() = settings;
+        self.ivars().invalid.borrow_mut().clear();
+    }
+
+    pub fn action(&self) -> Action {
+        self.ivars().action.get()
+    }
+
+    /// The edited bundle information.
+    ///
+    /// Call `end_editing` first, to make sure that changes to the currently
+    /// focused text field are included.
+    pub fn info(&self) -> BundleInformation {
+        let mut info = self
+            .ivars()
+            .info
+            .borrow()
+            .clone()
+            .expect("initialized info");
+        // Parameters that were added but never named are dropped
+        info.player.parameters.retain(|(name, _)| !name.is_empty());
+        info
+    }
+
+    /// The edited item settings.
+    pub fn settings(&self) -> ItemSettings {
+        self.ivars().settings.borrow().clone()
+    }
+
+    /// Resign the first responder, which commits pending text field changes.
+    pub fn end_editing(&self) {
+        if let Some(view) = self.view() {
+            unsafe { view.endEditing(true) };
+        }
+    }
+
+    fn with_info(&self, f: impl FnOnce(&mut BundleInformation)) {
+        f(self
+            .ivars()
+            .info
+            .borrow_mut()
+            .as_mut()
+            .expect("initialized info"));
+    }
+
+    /// Whether the form can be saved, otherwise tell the user what to fix.
+    fn can_save(&self) -> bool {
+        // Apply the focused text field, so that it is validated too
+        self.end_editing();
+
+        let invalid: Vec<&str> = self.ivars().invalid.borrow().keys().copied().collect();
+        let message = if !invalid.is_empty() {
+            format!("The following fields are invalid:\n{}", invalid.join("\n"))
+        } else if self.action() == Action::New && self.info().url == placeholder_url() {
+            "Enter the URL of the movie.".to_string()
+        } else {
+            return true;
+        };
+
+        let mtm = MainThreadMarker::from(self);
+        unsafe {
+            let alert = UIAlertController::alertControllerWithTitle_message_preferredStyle(
+                Some(ns_string!("Cannot Save")),
+                Some(&NSString::from_str(&message)),
+                UIAlertControllerStyle::Alert,
+                mtm,
+            );
+            alert.addAction(&UIAlertAction::actionWithTitle_style_handler(
+                Some(ns_string!("OK")),
+                UIAlertActionStyle::Default,
+                ptr::null_mut(),
+                mtm,
+            ));
+            self.presentViewController_animated_completion(&alert, true, None);
+        }
+        false
+    }
+
+    /// Show `text` in a text field, or the invalid text the user last
+    /// entered there.
+    fn set_input_text(&self, input: &UITextField, label: &'static str, text: Option<&str>) {
+        let invalid = self.ivars().invalid.borrow().get(label).cloned();
+        let text = invalid.as_deref().or(text);
+        unsafe { input.setText(text.map(NSString::from_str).as_deref()) };
+        set_invalid_color(input, invalid.is_some());
+    }
+
+    /// Apply the text of a text field, and highlight it if it was invalid.
+    fn apply_text(
+        &self,
+        input: &UITextField,
+        label: &'static str,
+        f: impl FnOnce(&mut BundleInformation, &str) -> Result<(), String>,
+    ) {
+        let text = unsafe { input.text() }
+            .map(|text| text.to_string())
+            .unwrap_or_default();
+        let mut res = Ok(());
+        self.with_info(|info| res = f(info, &text));
+        let mut invalid = self.ivars().invalid.borrow_mut();
+        match res {
+            Ok(()) => {
+                invalid.remove(label);
+            }
+            Err(err) => {
+                tracing::warn!(text, "invalid input: {err}");
+                invalid.insert(label, text);
+            }
+        }
+        set_invalid_color(input, invalid.contains_key(label));
+    }
+
+    fn did_select(&self, table_view: &UITableView, index_path: &NSIndexPath) {
+        let section = unsafe { index_path.section() } as usize;
+        let row = unsafe { index_path.row() } as usize;
+        if PARAMETERS_SECTION != section {
+            return;
+        }
+        unsafe { table_view.deselectRowAtIndexPath_animated(index_path, true) };
+
+        // Tapping the last row in the parameters section adds a parameter
+        let num_parameters = self
+            .ivars()
+            .info
+            .borrow()
+            .as_ref()
+            .expect("initialized info")
+            .player
+            .parameters
+            .len();
+        if num_parameters == row {
+            self.with_info(|info| info.player.parameters.push((String::new(), String::new())));
+            unsafe {
+                table_view.insertRowsAtIndexPaths_withRowAnimation(
+                    &NSArray::from_slice(&[index_path]),
+                    UITableViewRowAnimation::Automatic,
+                );
+            }
+        }
+    }
+
+    /// Revoke the user's answer for a connection, so that they are asked
+    /// again.
+    fn forget_socket(&self, table_view: &UITableView, index_path: &NSIndexPath) {
+        let row = unsafe { index_path.row() } as usize;
+        {
+            let mut settings = self.ivars().settings.borrow_mut();
+            let num_allowed = settings.socket_allowed.len();
+            if row < num_allowed {
+                settings.socket_allowed.remove(row);
+            } else {
+                settings.socket_denied.remove(row - num_allowed);
+            }
+        }
+        unsafe {
+            table_view.deleteRowsAtIndexPaths_withRowAnimation(
+                &NSArray::from_slice(&[index_path]),
+                UITableViewRowAnimation::Automatic,
+            );
+        }
     }
 
     fn view_did_load(&self) {
@@ -378,19 +789,55 @@ impl EditController {
         unsafe { table.flashScrollIndicators() };
     }
 
+    /// Apply changes to `input` when the user is done editing it.
+    fn on_text_change(
+        &self,
+        input: &UITextField,
+        label: &'static str,
+        f: impl Fn(&mut BundleInformation, &str) -> Result<(), String> + 'static,
+    ) {
+        let weak_input = Weak::new(input);
+        let this = Weak::new(self);
+        set_control_action(input, UIControlEvents::EditingDidEnd, move || {
+            if let (Some(this), Some(input)) = (this.load(), weak_input.load()) {
+                this.apply_text(&input, label, &f);
+            }
+        });
+    }
+
+    fn select_action(
+        &self,
+        set_variant: impl Fn(&Self, Option<&'static str>) + 'static,
+        variant: Option<&'static str>,
+    ) -> Retained<UIAction> {
+        let mtm = MainThreadMarker::from(self);
+        let this = Weak::new(self);
+        action(mtm, move || {
+            if let Some(this) = this.load() {
+                set_variant(&this, variant);
+            }
+        })
+    }
+
     fn cell_at_index_path(
         &self,
         table_view: &UITableView,
         index_path: &NSIndexPath,
     ) -> Retained<UITableViewCell> {
-        let mtm = MainThreadMarker::from(self);
         let info = self.ivars().info.borrow();
         let info = info.as_ref().expect("initialized info");
         let options = &info.player;
+        let settings = self.ivars().settings.borrow();
         unsafe {
             let section = index_path.section() as usize;
             let row = index_path.row() as usize;
-            if FORM.len() == section {
+            if SOCKETS_SECTION == section {
+                let (address, allowed) = remembered_sockets(&settings)
+                    .nth(row)
+                    .expect("row for remembered connection");
+                return socket_cell(table_view, address, allowed);
+            }
+            if PARAMETERS_SECTION == section {
                 if options.parameters.len() == row {
                     return table_view.dequeueReusableCellWithIdentifier_forIndexPath(
                         ns_string!("movie-parameter-add"),
@@ -406,8 +853,16 @@ impl EditController {
                 let subviews = cell.contentView().subviews();
                 let ui_param = Retained::cast::<UITextField>(subviews.objectAtIndex(1));
                 ui_param.setText(Some(&NSString::from_str(param)));
+                self.on_text_change(&ui_param, PARAMETER_LABEL, move |info, text| {
+                    info.player.parameters[row].0 = text.to_string();
+                    Ok(())
+                });
                 let ui_value = Retained::cast::<UITextField>(subviews.objectAtIndex(2));
                 ui_value.setText(Some(&NSString::from_str(value)));
+                self.on_text_change(&ui_value, PARAMETER_LABEL, move |info, text| {
+                    info.player.parameters[row].1 = text.to_string();
+                    Ok(())
+                });
 
                 return cell;
             }
@@ -421,7 +876,13 @@ impl EditController {
                     let input = Retained::cast::<UITextField>(
                         cell.contentView().subviews().objectAtIndex(0),
                     );
-                    input.setText(Some(&NSString::from_str(&info.name)));
+                    // The cell is shared with the URL
+                    input.setPlaceholder(Some(ns_string!("Name")));
+                    self.set_input_text(&input, NAME_LABEL, Some(&info.name));
+                    self.on_text_change(&input, NAME_LABEL, |info, text| {
+                        info.name = text.to_string();
+                        Ok(())
+                    });
                     cell
                 }
                 // TODO
@@ -433,10 +894,24 @@ impl EditController {
                     let input = Retained::cast::<UITextField>(
                         cell.contentView().subviews().objectAtIndex(0),
                     );
-                    input.setText(Some(&NSString::from_str(&info.url.to_string())));
+                    input.setPlaceholder(Some(ns_string!("URL")));
+                    // New items start out without a URL
+                    let url = Some(info.url.to_string()).filter(|_| info.url != placeholder_url());
+                    self.set_input_text(&input, SOURCE_LABEL, url.as_deref());
+                    self.on_text_change(&input, SOURCE_LABEL, |info, text| {
+                        info.url = text
+                            .trim()
+                            .parse()
+                            .map_err(|err: ParseError| err.to_string())?;
+                        Ok(())
+                    });
                     cell
                 }
-                FormElement::String { label, text } => {
+                FormElement::String {
+                    label,
+                    text,
+                    set_text,
+                } => {
                     let cell = table_view.dequeueReusableCellWithIdentifier_forIndexPath(
                         ns_string!("string"),
                         index_path,
@@ -447,69 +922,47 @@ impl EditController {
                     ui_label.setText(Some(&NSString::from_str(label)));
 
                     let input = Retained::cast::<UITextField>(subviews.objectAtIndex(1));
-                    input.setText(text(&options).map(|s| NSString::from_str(&s)).as_deref());
+                    self.set_input_text(&input, label, text(&options).as_deref());
+                    self.on_text_change(&input, label, move |info, text| {
+                        set_text(&mut info.player, text)
+                    });
                     cell
                 }
                 FormElement::Select {
                     label,
                     variants,
                     enabled_variant,
+                    set_variant,
+                } => self.select_cell(
+                    table_view,
+                    index_path,
+                    label,
+                    variants,
+                    enabled_variant(&options),
+                    move |this, variant| {
+                        this.with_info(|info| set_variant(&mut info.player, variant));
+                    },
+                ),
+                FormElement::SettingSelect {
+                    label,
+                    variants,
+                    enabled_variant,
+                    set_variant,
+                } => self.select_cell(
+                    table_view,
+                    index_path,
+                    label,
+                    variants,
+                    enabled_variant(&settings),
+                    move |this, variant| {
+                        set_variant(&mut this.ivars().settings.borrow_mut(), variant)
+                    },
+                ),
+                FormElement::Bool {
+                    label,
+                    value,
+                    set_value,
                 } => {
-                    let cell = table_view.dequeueReusableCellWithIdentifier_forIndexPath(
-                        ns_string!("select"),
-                        index_path,
-                    );
-                    let subviews = cell.contentView().subviews();
-
-                    let ui_label = Retained::cast::<UILabel>(subviews.objectAtIndex(0));
-                    ui_label.setText(Some(&NSString::from_str(label)));
-
-                    // Set menu
-                    let enabled_variant = enabled_variant(&options);
-                    let button = Retained::cast::<UIButton>(subviews.objectAtIndex(1));
-                    // We have to use UIAction here, UICommand seems to be broken
-                    let block = RcBlock::new(|_| {});
-                    let block_ptr: *const Block<_> = &*block;
-                    let default_item = UIAction::actionWithHandler(block_ptr.cast_mut(), mtm);
-                    default_item.setTitle(ns_string!("Default"));
-                    if enabled_variant.is_none() {
-                        default_item.setState(UIMenuElementState::On);
-                    }
-
-                    let children: Retained<NSArray<_>> = variants
-                        .iter()
-                        .map(|title| {
-                            let cmd = UIAction::actionWithHandler(block_ptr.cast_mut(), mtm);
-                            cmd.setTitle(&NSString::from_str(title));
-                            if enabled_variant == Some(title) {
-                                cmd.setState(UIMenuElementState::On);
-                            }
-                            Retained::into_super(cmd)
-                        })
-                        .collect();
-                    button.setMenu(Some(
-                        &UIMenu::menuWithTitle_image_identifier_options_children(
-                            ns_string!(""),
-                            None,
-                            None,
-                            UIMenuOptions::SingleSelection,
-                            &NSArray::from_slice(&[
-                                &**default_item,
-                                &*UIMenu::menuWithTitle_image_identifier_options_children(
-                                    ns_string!(""),
-                                    None,
-                                    None,
-                                    UIMenuOptions::DisplayInline | UIMenuOptions::SingleSelection,
-                                    &children,
-                                    mtm,
-                                ),
-                            ]),
-                            mtm,
-                        ),
-                    ));
-                    cell
-                }
-                FormElement::Bool { label, value } => {
                     let cell = table_view.dequeueReusableCellWithIdentifier_forIndexPath(
                         ns_string!("bool"),
                         index_path,
@@ -525,9 +978,147 @@ impl EditController {
                         Some(false) => 1,
                         Some(true) => 2,
                     });
+                    let weak_control = Weak::new(&*control);
+                    let this = Weak::new(self);
+                    set_control_action(&control, UIControlEvents::ValueChanged, move || {
+                        let (Some(this), Some(control)) = (this.load(), weak_control.load()) else {
+                            return;
+                        };
+                        let value = match unsafe { control.selectedSegmentIndex() } {
+                            1 => Some(false),
+                            2 => Some(true),
+                            _ => None,
+                        };
+                        this.with_info(|info| set_value(&mut info.player, value));
+                    });
                     cell
                 }
             }
         }
     }
+
+    fn select_cell(
+        &self,
+        table_view: &UITableView,
+        index_path: &NSIndexPath,
+        label: &str,
+        variants: &'static [&'static str],
+        enabled_variant: Option<&'static str>,
+        set_variant: impl Fn(&Self, Option<&'static str>) + Clone + 'static,
+    ) -> Retained<UITableViewCell> {
+        let mtm = MainThreadMarker::from(self);
+        unsafe {
+            let cell = table_view
+                .dequeueReusableCellWithIdentifier_forIndexPath(ns_string!("select"), index_path);
+            let subviews = cell.contentView().subviews();
+
+            let ui_label = Retained::cast::<UILabel>(subviews.objectAtIndex(0));
+            ui_label.setText(Some(&NSString::from_str(label)));
+
+            // Set menu
+            let button = Retained::cast::<UIButton>(subviews.objectAtIndex(1));
+            // We have to use UIAction here, UICommand seems to be broken
+            let default_item = self.select_action(set_variant.clone(), None);
+            default_item.setTitle(ns_string!("Default"));
+            if enabled_variant.is_none() {
+                default_item.setState(UIMenuElementState::On);
+            }
+
+            let children: Retained<NSArray<_>> = variants
+                .iter()
+                .map(|title| {
+                    let cmd = self.select_action(set_variant.clone(), Some(title));
+                    cmd.setTitle(&NSString::from_str(title));
+                    if enabled_variant == Some(title) {
+                        cmd.setState(UIMenuElementState::On);
+                    }
+                    Retained::into_super(cmd)
+                })
+                .collect();
+            button.setMenu(Some(
+                &UIMenu::menuWithTitle_image_identifier_options_children(
+                    ns_string!(""),
+                    None,
+                    None,
+                    UIMenuOptions::SingleSelection,
+                    &NSArray::from_slice(&[
+                        &**default_item,
+                        &*UIMenu::menuWithTitle_image_identifier_options_children(
+                            ns_string!(""),
+                            None,
+                            None,
+                            UIMenuOptions::DisplayInline | UIMenuOptions::SingleSelection,
+                            &children,
+                            mtm,
+                        ),
+                    ]),
+                    mtm,
+                ),
+            ));
+            cell
+        }
+    }
+}
+
+/// A cell showing the user's answer for a connection.
+fn socket_cell(
+    table_view: &UITableView,
+    address: &str,
+    allowed: bool,
+) -> Retained<UITableViewCell> {
+    let mtm = MainThreadMarker::from(table_view);
+    let identifier = ns_string!("remembered-socket");
+    unsafe {
+        // Not in the Storyboard, since it only needs the system style
+        let cell = table_view
+            .dequeueReusableCellWithIdentifier(identifier)
+            .unwrap_or_else(|| {
+                UITableViewCell::initWithStyle_reuseIdentifier(
+                    mtm.alloc(),
+                    UITableViewCellStyle::Value1,
+                    Some(identifier),
+                )
+            });
+        let content = UIListContentConfiguration::valueCellConfiguration(mtm);
+        content.setText(Some(&NSString::from_str(address)));
+        content.setSecondaryText(Some(if allowed {
+            ns_string!("Allowed")
+        } else {
+            ns_string!("Denied")
+        }));
+        cell.setContentConfiguration(Some(ProtocolObject::from_ref(&*content)));
+        cell.setSelectionStyle(UITableViewCellSelectionStyle::None);
+        cell
+    }
+}
+
+fn set_invalid_color(input: &UITextField, invalid: bool) {
+    let color = if invalid {
+        unsafe { UIColor::systemRedColor() }
+    } else {
+        unsafe { UIColor::labelColor() }
+    };
+    unsafe { input.setTextColor(Some(&color)) };
+}
+
+/// Replace the action that `control` runs on `events`.
+///
+/// Cells are reused, so we use a fixed identifier to avoid accumulating
+/// actions from previous uses of the cell.
+fn set_control_action(control: &UIControl, events: UIControlEvents, f: impl Fn() + 'static) {
+    let mtm = MainThreadMarker::from(control);
+    let identifier = ns_string!("edit-controller-action");
+    let block = RcBlock::new(move |_: NonNull<UIAction>| f());
+    let block_ptr: *const Block<_> = &*block;
+    unsafe {
+        control.removeActionForIdentifier_forControlEvents(identifier, events);
+        let action = UIAction::actionWithTitle_image_identifier_handler(
+            ns_string!(""),
+            None,
+            Some(identifier),
+            block_ptr.cast_mut(),
+            mtm,
+        );
+        control.addAction_forControlEvents(&action, events);
+    }
 }
@@ -0,0 +1,96 @@
+//! Polling the navigator's futures on the main thread.
+use std::ffi::c_void;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock, Weak};
+
+use objc2_foundation::{MainThreadBound, MainThreadMarker};
+use ruffle_frontend_utils::backends::executor::{AsyncExecutor, PollRequester};
+
+use crate::launch::panic_message;
+
+pub type Executor = AsyncExecutor<MainThreadPoller>;
+
+/// Requests that the executor is polled on the main thread.
+///
+/// Futures may be woken from any thread, e.g. when network I/O completes on
+/// the Tokio runtime, so polls are dispatched asynchronously to the main
+/// queue, which the main run loop drains. The player is thus only ever
+/// accessed from the main thread, and a poll can never happen from inside
+/// another poll.
+#[derive(Clone)]
+pub struct MainThreadPoller(Arc<Shared>);
+
+struct Shared {
+    /// Set while a poll is dispatched, so that we don't queue up several.
+    scheduled: AtomicBool,
+    executor: OnceLock<MainThreadBound<Weak<Executor>>>,
+}
+
+impl MainThreadPoller {
+    pub fn new() -> Self {
+        Self(Arc::new(Shared {
+            scheduled: AtomicBool::new(false),
+            executor: OnceLock::new(),
+        }))
+    }
+
+    /// Set the executor to poll.
+    ///
+    /// Only a weak reference is kept, the owner of the executor decides how
+    /// long it lives.
+    pub fn set_executor(&self, executor: &Arc<Executor>, mtm: MainThreadMarker) {
+        let executor = MainThreadBound::new(Arc::downgrade(executor), mtm);
+        if self.0.executor.set(executor).is_err() {
+            panic!("executor set only once");
+        }
+    }
+}
+
+impl PollRequester for MainThreadPoller {
+    fn request_poll(&self) {
+        if self.0.scheduled.swap(true, Ordering::AcqRel) {
+            // Already dispatched, that poll will handle this request too.
+            return;
+        }
+        let context = Arc::into_raw(self.0.clone()).cast_mut().cast::<c_void>();
+        unsafe { dispatch_async_f(&_dispatch_main_q, context, poll_on_main) };
+    }
+}
+
+extern "C" fn poll_on_main(context: *mut c_void) {
+    // SAFETY: Created with `Arc::into_raw` in `request_poll`.
+    let shared = unsafe { Arc::from_raw(context.cast_const().cast::<Shared>()) };
+    let mtm = MainThreadMarker::new().expect("main queue runs on the main thread");
+
+    // Cleared before polling, so that futures woken during the poll are
+    // polled again afterwards.
+    shared.scheduled.store(false, Ordering::Release);
+    let Some(executor) = shared.executor.get().and_then(|e| e.get(mtm).upgrade()) else {
+        // The player was closed
+        return;
+    };
+
+    // Unwinding out of an `extern "C"` function aborts.
+    if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(|| executor.poll_all())) {
+        tracing::error!("executor panicked: {}", panic_message(&*payload));
+    }
+}
+
+/// `dispatch_queue_t`
+#[repr(C)]
+struct DispatchQueue {
+    _priv: [u8; 0],
+}
+
+// libdispatch is part of libSystem, so it's always linked.
+extern "C" {
+    /// The queue returned by `dispatch_get_main_queue()`.
+    static _dispatch_main_q: DispatchQueue;
+
+    fn dispatch_async_f(
+        queue: *const DispatchQueue,
+        context: *mut c_void,
+        work: extern "C" fn(*mut c_void),
+    );
+}
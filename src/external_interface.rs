@@ -0,0 +1,38 @@
+//! A dummy ExternalInterface, for movies that refuse to run without one.
+use ruffle_core::context::UpdateContext;
+use ruffle_core::external::{ExternalInterfaceProvider, Value as ExternalValue};
+use url::Url;
+
+/// Pretends to be a browser hosting the movie at `spoof_url`.
+pub struct DummyExternalInterfaceProvider {
+    pub spoof_url: Option<Url>,
+}
+
+impl ExternalInterfaceProvider for DummyExternalInterfaceProvider {
+    fn call_method(
+        &self,
+        _context: &mut UpdateContext<'_>,
+        name: &str,
+        args: &[ExternalValue],
+    ) -> ExternalValue {
+        if let Some(url) = &self.spoof_url {
+            if name == "window.location.href.toString" || name == "window.location.href.valueOf" {
+                return ExternalValue::String(url.to_string());
+            }
+            if let ("eval", [ExternalValue::String(code)]) = (name, args) {
+                if code == "window.location.href" || code == "document.location.href" {
+                    return ExternalValue::String(url.to_string());
+                }
+            }
+        }
+
+        tracing::warn!("Trying to call unknown ExternalInterface method: {name}");
+        ExternalValue::Undefined
+    }
+
+    fn on_callback_available(&self, _name: &str) {}
+
+    fn get_id(&self) -> Option<String> {
+        None
+    }
+}
@@ -0,0 +1,286 @@
+//! Running movies without a window, GPU or audio device.
+//!
+//! Used by `run_swf --headless`, e.g. for smoke testing movies on Linux.
+use std::fs::File;
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use ruffle_core::backend::audio::NullAudioBackend;
+use ruffle_core::backend::log::LogBackend;
+use ruffle_core::backend::navigator::{NavigatorBackend, OpenURLMode, Request, SocketMode};
+use ruffle_core::backend::storage::MemoryStorageBackend;
+use ruffle_core::{Player, PlayerBuilder, ViewportDimensions};
+use ruffle_frontend_utils::backends::executor::{AsyncExecutor, PollRequester};
+use ruffle_frontend_utils::backends::navigator::{ExternalNavigatorBackend, NavigatorInterface};
+use ruffle_render::backend::null::NullRenderer;
+use url::Url;
+
+use crate::launch::{
+    apply_player_options, apply_player_options_after_build, load_root_movie, panic_message, Launch,
+    LaunchError,
+};
+use crate::sandbox::FileSandbox;
+use crate::scheduler::FrameScheduler;
+use crate::storage::DiskStorageBackend;
+
+/// How long to run the movie for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RunLimit {
+    /// Run this many frames as fast as possible.
+    ///
+    /// The movie's clock advances by one frame each step, so the result
+    /// doesn't depend on how fast the machine is.
+    Frames(u32),
+    /// Run in real time, for this long.
+    Duration(Duration),
+}
+
+/// Statistics about a completed run.
+#[derive(Debug, Clone, Default)]
+pub struct RunSummary {
+    /// The number of times the player was ticked.
+    pub ticks: u32,
+    /// The total time spent ticking the player.
+    pub tick_time: Duration,
+    /// The longest time spent in a single tick.
+    pub max_tick_time: Duration,
+    /// The time from starting to load the movie until the run finished.
+    pub elapsed: Duration,
+}
+
+/// Polls happen in the run loop, so just remember that one was requested.
+#[derive(Clone, Default)]
+struct Poller(Arc<AtomicBool>);
+
+impl PollRequester for Poller {
+    fn request_poll(&self) {
+        self.0.store(true, Ordering::Release);
+    }
+}
+
+/// There is nobody to ask, so websites are never opened, and sockets are only
+/// allowed if the settings say so.
+#[derive(Clone)]
+struct Navigator {
+    sandbox: Arc<FileSandbox>,
+}
+
+impl NavigatorInterface for Navigator {
+    fn navigate_to_website(&self, url: Url, _ask: bool) {
+        tracing::info!(%url, "not opening website while headless");
+    }
+
+    fn open_file(&self, path: &Path) -> io::Result<File> {
+        self.sandbox.open(path)
+    }
+
+    async fn confirm_socket(&self, host: &str, port: u16) -> bool {
+        tracing::info!(host, port, "denying socket connection while headless");
+        false
+    }
+}
+
+/// Load the movie, and run it until the limit is reached.
+///
+/// Panics in the player are caught and returned as
+/// [`LaunchError::Panicked`]. AVM output goes to `log`.
+pub fn run_headless(
+    launch: &Launch,
+    limit: RunLimit,
+    log: impl LogBackend + 'static,
+) -> Result<RunSummary, LaunchError> {
+    let start = Instant::now();
+    // Network I/O is driven by the runtime's worker threads, like in the app.
+    let runtime = tokio::runtime::Runtime::new()?;
+    let _guard = runtime.enter();
+
+    let info = &launch.info;
+    let options = &info.player;
+    let settings = &launch.settings;
+
+    let poller = Poller::default();
+    let (executor, future_spawner) = AsyncExecutor::new(poller.clone());
+    let mut navigator = ExternalNavigatorBackend::new(
+        options.base.clone().unwrap_or_else(|| info.url.clone()),
+        options.referer.clone(),
+        options.cookie.clone(),
+        future_spawner,
+        None,
+        options.upgrade_to_https.unwrap_or_default(),
+        OpenURLMode::Deny,
+        settings.socket_allowed.iter().cloned().collect(),
+        settings.socket_mode.unwrap_or(SocketMode::Deny),
+        Rc::new(launch.content()?),
+        Navigator {
+            sandbox: Arc::new(launch.file_sandbox()),
+        },
+    );
+
+    tracing::info!(url = %info.url, "loading root movie");
+    let fetch = navigator.fetch(Request::get(info.url.to_string()));
+    let movie = runtime.block_on(load_root_movie(
+        fetch,
+        options.parameters.clone(),
+        |_, _| {},
+    ))?;
+
+    let dimensions = ViewportDimensions {
+        width: movie.width().to_pixels() as u32,
+        height: movie.height().to_pixels() as u32,
+        scale_factor: 1.0,
+    };
+    let mut builder = PlayerBuilder::new()
+        .with_renderer(NullRenderer::new(dimensions))
+        .with_audio(NullAudioBackend::new())
+        .with_navigator(navigator)
+        .with_log(log)
+        .with_movie(movie);
+    builder = apply_player_options(builder, options);
+    builder = match launch.app_data_dir() {
+        Some(app_data_dir) => builder.with_storage(Box::new(DiskStorageBackend::new(app_data_dir))),
+        None => builder.with_storage(Box::new(MemoryStorageBackend::default())),
+    };
+
+    let player = catch_panic(|| {
+        let player = builder.build();
+        apply_player_options_after_build(&mut player.lock().unwrap(), options);
+        player
+    })?;
+
+    let mut summary = RunSummary::default();
+    let mut tick = |f: &mut dyn FnMut(&mut Player) -> Duration| -> Result<Duration, LaunchError> {
+        let tick_start = Instant::now();
+        let res = catch_panic(|| f(&mut player.lock().expect("player lock")));
+        let tick_time = tick_start.elapsed();
+        summary.ticks += 1;
+        summary.tick_time += tick_time;
+        summary.max_tick_time = summary.max_tick_time.max(tick_time);
+
+        // Run the futures that were woken while ticking, e.g. loaders
+        if poller.0.swap(false, Ordering::AcqRel) {
+            catch_panic(|| executor.poll_all())?;
+        }
+        res
+    };
+
+    match limit {
+        RunLimit::Frames(frames) => {
+            for _ in 0..frames {
+                tick(&mut |player| {
+                    let frame_time = 1000.0 / player.frame_rate();
+                    player.tick(frame_time);
+                    Duration::ZERO
+                })?;
+            }
+        }
+        RunLimit::Duration(duration) => {
+            let mut scheduler = FrameScheduler::default();
+            scheduler.start();
+            let end = Instant::now() + duration;
+            while Instant::now() < end {
+                let dt = scheduler.tick().expect("scheduler is running");
+                let time_til_next_frame = tick(&mut |player| {
+                    player.tick(dt.as_secs_f64() * 1000.0);
+                    player.time_til_next_frame()
+                })?;
+                let next_tick = scheduler
+                    .next_tick(time_til_next_frame)
+                    .expect("scheduler is running");
+                thread::sleep(scheduler.time_until(next_tick.min(end)));
+            }
+        }
+    }
+
+    catch_panic(|| player.lock().expect("player lock").flush_shared_objects())?;
+    summary.elapsed = start.elapsed();
+    tracing::info!(?summary, "finished running movie");
+    Ok(summary)
+}
+
+fn catch_panic<R>(f: impl FnOnce() -> R) -> Result<R, LaunchError> {
+    panic::catch_unwind(AssertUnwindSafe(f))
+        .map_err(|payload| LaunchError::Panicked(panic_message(&*payload)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use super::*;
+
+    /// A 100x100 AVM1 movie with one frame that traces each of `messages`.
+    fn trace_swf(messages: &[&str]) -> Vec<u8> {
+        let mut actions = vec![];
+        for message in messages {
+            // ActionPush of a string, then ActionTrace
+            actions.push(0x96);
+            actions.extend_from_slice(&(message.len() as u16 + 2).to_le_bytes());
+            actions.push(0);
+            actions.extend_from_slice(message.as_bytes());
+            actions.extend_from_slice(&[0, 0x26]);
+        }
+        actions.push(0);
+
+        let mut body = vec![];
+        // The frame size in twips, then 24 fps and one frame
+        body.extend_from_slice(&[0x78, 0x00, 0x00, 0xfa, 0x00, 0x00, 0x03, 0xe8, 0x00]);
+        body.extend_from_slice(&[0x00, 24, 1, 0]);
+        // DoAction, with a long header
+        body.extend_from_slice(&((12 << 6) | 0x3f_u16).to_le_bytes());
+        body.extend_from_slice(&(actions.len() as u32).to_le_bytes());
+        body.extend_from_slice(&actions);
+        // ShowFrame, then End
+        body.extend_from_slice(&(1_u16 << 6).to_le_bytes());
+        body.extend_from_slice(&[0, 0]);
+
+        let mut swf = b"FWS\x0a".to_vec();
+        swf.extend_from_slice(&(body.len() as u32 + 8).to_le_bytes());
+        swf.extend_from_slice(&body);
+        swf
+    }
+
+    #[derive(Clone, Default)]
+    struct CollectLogBackend(Rc<RefCell<Vec<String>>>);
+
+    impl LogBackend for CollectLogBackend {
+        fn avm_trace(&self, message: &str) {
+            self.0.borrow_mut().push(message.to_string());
+        }
+
+        fn avm_warning(&self, message: &str) {
+            self.0.borrow_mut().push(format!("warning: {message}"));
+        }
+    }
+
+    #[test]
+    fn traces() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("trace.swf");
+        std::fs::write(&path, trace_swf(&["hello", "world"])).unwrap();
+        let launch = Launch::transient(&path).unwrap();
+
+        let log = CollectLogBackend::default();
+        let summary = run_headless(&launch, RunLimit::Frames(3), log.clone()).unwrap();
+        assert_eq!(summary.ticks, 3);
+        // The frame's actions only run once, even though it's ticked again
+        assert_eq!(*log.0.borrow(), ["hello", "world"]);
+    }
+
+    #[test]
+    fn not_a_movie() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("junk.swf");
+        std::fs::write(&path, b"not a movie").unwrap();
+        let launch = Launch::transient(&path).unwrap();
+
+        let log = CollectLogBackend::default();
+        assert!(run_headless(&launch, RunLimit::Frames(1), log.clone()).is_err());
+        assert!(log.0.borrow().is_empty());
+    }
+}
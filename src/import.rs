@@ -0,0 +1,492 @@
+//! Importing files into the library.
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read};
+use std::path::Path;
+
+use ruffle_frontend_utils::bundle::info::{
+    BundleInformation, BundleInformationParseError, BUNDLE_INFORMATION_FILENAME,
+};
+use ruffle_frontend_utils::player_options::PlayerOptions;
+use url::Url;
+use zip::result::ZipError;
+use zip::ZipArchive;
+
+use crate::library::{Library, LibraryError, LibraryItem, CONTENT_DIR};
+use crate::settings::{read_bundle_information, write_bundle_information};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ImportError {
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+    #[error("library error: {0}")]
+    Library(#[from] LibraryError),
+    #[error("file name is not valid: {0:?}")]
+    InvalidFileName(String),
+    #[error("not an SWF file")]
+    NotSwf,
+    #[error("invalid zip archive: {0}")]
+    Zip(#[from] ZipError),
+    #[error("bundle contains an unsafe path: {0:?}")]
+    UnsafePath(String),
+    #[error("bundle is missing {BUNDLE_INFORMATION_FILENAME}")]
+    MissingBundleInformation,
+    #[error("invalid bundle information: {0}")]
+    InvalidBundleInformation(#[from] BundleInformationParseError),
+}
+
+/// Whether the data starts with one of the SWF signatures.
+///
+/// `FWS` is uncompressed, `CWS` is zlib compressed and `ZWS` is LZMA
+/// compressed.
+pub fn is_swf_header(data: &[u8]) -> bool {
+    matches!(data.get(..3), Some(b"FWS" | b"CWS" | b"ZWS"))
+}
+
+fn check_swf_header(path: &Path) -> Result<(), ImportError> {
+    let mut header = [0; 3];
+    match File::open(path)?.read_exact(&mut header) {
+        Ok(()) => {}
+        // Too short to be an SWF
+        Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Err(ImportError::NotSwf),
+        Err(err) => return Err(err.into()),
+    }
+    if is_swf_header(&header) {
+        Ok(())
+    } else {
+        Err(ImportError::NotSwf)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentKind {
+    /// A Ruffle Bundle, either zipped or unpacked.
+    Bundle,
+    Swf,
+}
+
+/// Guess what a file holds from its path.
+///
+/// Directories and `.ruf` files are treated as bundles, everything else as
+/// SWFs, which is checked when they are opened.
+pub fn detect_content_kind(path: &Path) -> ContentKind {
+    let is_bundle = path.is_dir()
+        || path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("ruf"));
+    if is_bundle {
+        ContentKind::Bundle
+    } else {
+        ContentKind::Swf
+    }
+}
+
+/// Import a Ruffle Bundle or an SWF, see [`detect_content_kind`].
+pub fn import_path(library: &Library, path: &Path) -> Result<LibraryItem, ImportError> {
+    match detect_content_kind(path) {
+        ContentKind::Bundle => import_ruf(library, path),
+        ContentKind::Swf => import_swf(library, path),
+    }
+}
+
+/// Import a bare SWF file by converting it to a Ruffle Bundle.
+///
+/// The bundle gets `name = "file_stem"` and `url = "file:///file_stem.swf"`.
+pub fn import_swf(library: &Library, path: &Path) -> Result<LibraryItem, ImportError> {
+    check_swf_header(path)?;
+
+    let file_stem = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .filter(|stem| !stem.is_empty())
+        .ok_or_else(|| ImportError::InvalidFileName(path.display().to_string()))?;
+    let file_name = format!("{file_stem}.swf");
+    // Pushing a path segment percent-encodes the file name as required.
+    let mut url = Url::parse("file:///").expect("valid base URL");
+    url.path_segments_mut()
+        .expect("file URL can be a base")
+        .pop_if_empty()
+        .push(&file_name);
+
+    let item = library.insert_with(|bundle_dir| {
+        let content_dir = bundle_dir.join(CONTENT_DIR);
+        fs::create_dir_all(&content_dir)?;
+        fs::copy(path, content_dir.join(&file_name))?;
+        fs::write(
+            bundle_dir.join(BUNDLE_INFORMATION_FILENAME),
+            write_bundle_information(&BundleInformation {
+                name: file_stem.to_string(),
+                url,
+                player: PlayerOptions::default(),
+            }),
+        )?;
+        Ok::<_, ImportError>(())
+    })?;
+
+    tracing::info!(id = %item.id, ?path, "imported SWF");
+    Ok(item)
+}
+
+/// Import a Ruffle Bundle, either zipped or unpacked.
+///
+/// The bundle is unpacked or copied into the library, and is validated by
+/// loading it as a bundle afterwards.
+pub fn import_ruf(library: &Library, path: &Path) -> Result<LibraryItem, ImportError> {
+    let item = library.insert_with(|bundle_dir| {
+        if path.is_dir() {
+            copy_dir(path, bundle_dir)?;
+        } else {
+            let mut archive = ZipArchive::new(BufReader::new(File::open(path)?))?;
+            unpack_zip(&mut archive, bundle_dir)?;
+        }
+
+        // Check this explicitly, to give a better error message than what
+        // loading the bundle would.
+        let info = match fs::read_to_string(bundle_dir.join(BUNDLE_INFORMATION_FILENAME)) {
+            Ok(info) => info,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                return Err(ImportError::MissingBundleInformation)
+            }
+            Err(err) => return Err(err.into()),
+        };
+        read_bundle_information(&info)?;
+        Ok(())
+    })?;
+
+    tracing::info!(id = %item.id, ?path, "imported bundle");
+    Ok(item)
+}
+
+fn copy_dir(src: &Path, dest: &Path) -> Result<(), ImportError> {
+    fs::create_dir_all(dest)?;
+
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        let out_path = dest.join(entry.file_name());
+        // Doesn't follow symlinks, which could point outside of the bundle.
+        let file_type = entry.file_type()?;
+        if file_type.is_symlink() {
+            tracing::warn!(path = ?entry.path(), "rejected symlink in bundle");
+            return Err(ImportError::UnsafePath(entry.path().display().to_string()));
+        } else if file_type.is_dir() {
+            copy_dir(&entry.path(), &out_path)?;
+        } else {
+            fs::copy(entry.path(), out_path)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn unpack_zip<R: io::Read + io::Seek>(
+    archive: &mut ZipArchive<R>,
+    dest: &Path,
+) -> Result<(), ImportError> {
+    fs::create_dir_all(dest)?;
+
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i)?;
+
+        // Rejects absolute paths and paths containing `..`.
+        let Some(relative_path) = entry.enclosed_name() else {
+            tracing::warn!(name = entry.name(), "rejected path in bundle");
+            return Err(ImportError::UnsafePath(entry.name().to_string()));
+        };
+        // Symlinks could point outside of the bundle.
+        if entry.is_symlink() {
+            tracing::warn!(name = entry.name(), "rejected symlink in bundle");
+            return Err(ImportError::UnsafePath(entry.name().to_string()));
+        }
+
+        let out_path = dest.join(relative_path);
+        if entry.is_dir() {
+            fs::create_dir_all(&out_path)?;
+        } else {
+            if let Some(parent) = out_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            io::copy(&mut entry, &mut File::create(&out_path)?)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::path::PathBuf;
+
+    use tempfile::TempDir;
+    use zip::write::SimpleFileOptions;
+    use zip::ZipWriter;
+
+    use super::*;
+
+    const BUNDLE_INFORMATION: &str = r#"
+[bundle]
+name = "Movie"
+url = "file:///movie.swf"
+"#;
+
+    fn library() -> (TempDir, Library) {
+        let dir = tempfile::tempdir().unwrap();
+        let library = Library::new(dir.path().join("library"));
+        (dir, library)
+    }
+
+    fn write_file(dir: &TempDir, name: &str, data: &[u8]) -> PathBuf {
+        let path = dir.path().join(name);
+        fs::write(&path, data).unwrap();
+        path
+    }
+
+    #[test]
+    fn swf_header() {
+        assert!(is_swf_header(b"FWS\x0a"));
+        assert!(is_swf_header(b"CWS\x0a"));
+        assert!(is_swf_header(b"ZWS\x0d"));
+        assert!(is_swf_header(b"FWS"));
+        assert!(!is_swf_header(b"PK\x03\x04"));
+        assert!(!is_swf_header(b"fws"));
+        assert!(!is_swf_header(b"FW"));
+        assert!(!is_swf_header(b""));
+    }
+
+    #[test]
+    fn swf() {
+        let (dir, library) = library();
+        for (name, data) in [
+            ("uncompressed.swf", &b"FWS\x0amovie"[..]),
+            ("zlib.swf", b"CWS\x0amovie"),
+            ("lzma.swf", b"ZWS\x0dmovie"),
+        ] {
+            let path = write_file(&dir, name, data);
+            let item = import_swf(&library, &path).unwrap();
+            let copied = library.content_dir(item.id).join(name);
+            assert_eq!(fs::read(copied).unwrap(), data);
+        }
+        assert_eq!(library.load().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn swf_name_and_url() {
+        let (dir, library) = library();
+        let path = write_file(&dir, "My Movie #1.swf", b"FWS\x0amovie");
+        let item = import_swf(&library, &path).unwrap();
+        assert_eq!(item.info.name, "My Movie #1");
+        assert_eq!(item.info.url.as_str(), "file:///My%20Movie%20%231.swf");
+        assert!(library
+            .content_dir(item.id)
+            .join("My Movie #1.swf")
+            .is_file());
+
+        // The bundle written to the library loads again
+        let loaded = library.load_item(item.id).unwrap();
+        assert_eq!(loaded.info.name, item.info.name);
+        assert_eq!(loaded.info.url, item.info.url);
+    }
+
+    #[test]
+    fn swf_extension_is_normalized() {
+        let (dir, library) = library();
+        let path = write_file(&dir, "movie.bin", b"FWS\x0amovie");
+        let item = import_swf(&library, &path).unwrap();
+        assert_eq!(item.info.url.as_str(), "file:///movie.swf");
+        assert!(library.content_dir(item.id).join("movie.swf").is_file());
+    }
+
+    #[test]
+    fn not_swf() {
+        let (dir, library) = library();
+        for (name, data) in [
+            ("junk.swf", &b"<html></html>"[..]),
+            ("short.swf", b"FW"),
+            ("empty.swf", b""),
+        ] {
+            let path = write_file(&dir, name, data);
+            assert!(
+                matches!(import_swf(&library, &path), Err(ImportError::NotSwf)),
+                "{name}"
+            );
+        }
+        assert!(library.load().unwrap().is_empty());
+    }
+
+    /// Write a zipped bundle with the given files.
+    fn write_zip(dir: &TempDir, files: &[(&str, &str)]) -> PathBuf {
+        let path = dir.path().join("bundle.ruf");
+        let mut zip = ZipWriter::new(File::create(&path).unwrap());
+        for (name, contents) in files {
+            zip.start_file(*name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(contents.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+        path
+    }
+
+    /// Write an unpacked bundle with the given files.
+    fn write_dir(dir: &TempDir, files: &[(&str, &str)]) -> PathBuf {
+        let path = dir.path().join("bundle");
+        for (name, contents) in files {
+            let file_path = path.join(name);
+            fs::create_dir_all(file_path.parent().unwrap()).unwrap();
+            fs::write(file_path, contents).unwrap();
+        }
+        path
+    }
+
+    /// Failed imports leave nothing behind in the library.
+    fn assert_empty(library: &Library) {
+        let leftovers = fs::read_dir(library.root()).map_or(0, |entries| entries.count());
+        assert_eq!(leftovers, 0);
+    }
+
+    #[test]
+    fn ruf_zip() {
+        let (dir, library) = library();
+        let path = write_zip(
+            &dir,
+            &[
+                (BUNDLE_INFORMATION_FILENAME, BUNDLE_INFORMATION),
+                ("content/movie.swf", "FWS"),
+                ("content/data/level.xml", "<level/>"),
+            ],
+        );
+        let item = import_ruf(&library, &path).unwrap();
+        assert_eq!(item.info.name, "Movie");
+        let content_dir = library.content_dir(item.id);
+        assert_eq!(
+            fs::read_to_string(content_dir.join("movie.swf")).unwrap(),
+            "FWS"
+        );
+        assert!(content_dir.join("data/level.xml").is_file());
+    }
+
+    #[test]
+    fn ruf_dir() {
+        let (dir, library) = library();
+        let path = write_dir(
+            &dir,
+            &[
+                (BUNDLE_INFORMATION_FILENAME, BUNDLE_INFORMATION),
+                ("content/movie.swf", "FWS"),
+                ("content/data/level.xml", "<level/>"),
+            ],
+        );
+        let item = import_path(&library, &path).unwrap();
+        assert_eq!(item.info.name, "Movie");
+        let content_dir = library.content_dir(item.id);
+        assert_eq!(
+            fs::read_to_string(content_dir.join("movie.swf")).unwrap(),
+            "FWS"
+        );
+        assert!(content_dir.join("data/level.xml").is_file());
+        // The original is left alone
+        assert!(path.join("content/movie.swf").is_file());
+    }
+
+    #[test]
+    fn zip_unsafe_paths() {
+        for name in ["../evil.txt", "content/../../evil.txt", "/tmp/evil.txt"] {
+            let (dir, library) = library();
+            let path = write_zip(
+                &dir,
+                &[
+                    (BUNDLE_INFORMATION_FILENAME, BUNDLE_INFORMATION),
+                    (name, "evil"),
+                ],
+            );
+            assert!(
+                matches!(
+                    import_ruf(&library, &path),
+                    Err(ImportError::UnsafePath(path)) if path == name
+                ),
+                "{name}"
+            );
+            assert!(!dir.path().join("evil.txt").exists());
+            assert_empty(&library);
+        }
+    }
+
+    #[test]
+    fn zip_symlink() {
+        let (dir, library) = library();
+        let path = dir.path().join("bundle.ruf");
+        let mut zip = ZipWriter::new(File::create(&path).unwrap());
+        let options = SimpleFileOptions::default();
+        zip.start_file(BUNDLE_INFORMATION_FILENAME, options)
+            .unwrap();
+        zip.write_all(BUNDLE_INFORMATION.as_bytes()).unwrap();
+        zip.add_symlink("content/passwd", "/etc/passwd", options)
+            .unwrap();
+        zip.finish().unwrap();
+
+        assert!(matches!(
+            import_ruf(&library, &path),
+            Err(ImportError::UnsafePath(path)) if path == "content/passwd"
+        ));
+        assert_empty(&library);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn dir_symlink() {
+        let (dir, library) = library();
+        let secret = write_file(&dir, "secret.txt", b"secret");
+        let path = write_dir(&dir, &[(BUNDLE_INFORMATION_FILENAME, BUNDLE_INFORMATION)]);
+        fs::create_dir(path.join("content")).unwrap();
+        std::os::unix::fs::symlink(&secret, path.join("content/secret.txt")).unwrap();
+
+        assert!(matches!(
+            import_ruf(&library, &path),
+            Err(ImportError::UnsafePath(_))
+        ));
+        assert_empty(&library);
+    }
+
+    #[test]
+    fn missing_bundle_information() {
+        let (dir, library) = library();
+        let path = write_zip(&dir, &[("content/movie.swf", "FWS")]);
+        assert!(matches!(
+            import_ruf(&library, &path),
+            Err(ImportError::MissingBundleInformation)
+        ));
+        assert_empty(&library);
+
+        let path = write_dir(&dir, &[("content/movie.swf", "FWS")]);
+        assert!(matches!(
+            import_ruf(&library, &path),
+            Err(ImportError::MissingBundleInformation)
+        ));
+        assert_empty(&library);
+    }
+
+    #[test]
+    fn invalid_bundle_information() {
+        let (dir, library) = library();
+        let invalid_url = "[bundle]\nname = \"Movie\"\nurl = \"not a url\"\n";
+        for info in ["not = [valid", invalid_url] {
+            let path = write_zip(&dir, &[(BUNDLE_INFORMATION_FILENAME, info)]);
+            assert!(
+                matches!(
+                    import_ruf(&library, &path),
+                    Err(ImportError::InvalidBundleInformation(_))
+                ),
+                "{info}"
+            );
+            assert_empty(&library);
+        }
+    }
+
+    #[test]
+    fn not_a_zip() {
+        let (dir, library) = library();
+        let path = write_file(&dir, "bundle.ruf", b"FWS\x0amovie");
+        assert!(matches!(
+            import_ruf(&library, &path),
+            Err(ImportError::Zip(_))
+        ));
+        assert_empty(&library);
+    }
+}
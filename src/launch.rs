@@ -0,0 +1,415 @@
+//! Describes what the player should run, and where it's loaded from.
+use std::any::Any;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use ruffle_core::backend::navigator::{ErrorResponse, OwnedFuture, SuccessResponse};
+use ruffle_core::config::Letterbox;
+use ruffle_core::loader;
+use ruffle_core::tag_utils::{self, SwfMovie};
+use ruffle_core::{LoadBehavior, Player, PlayerBuilder};
+use ruffle_frontend_utils::bundle::info::BundleInformation;
+use ruffle_frontend_utils::bundle::{Bundle, BundleError};
+use ruffle_frontend_utils::content::PlayingContent;
+use ruffle_frontend_utils::player_options::PlayerOptions;
+use ruffle_render::quality::StageQuality;
+use url::Url;
+use uuid::Uuid;
+
+use crate::external_interface::DummyExternalInterfaceProvider;
+use crate::import::{detect_content_kind, is_swf_header, ContentKind};
+use crate::library::{Library, LibraryError, LibraryItem, CONTENT_DIR};
+use crate::sandbox::FileSandbox;
+use crate::settings::ItemSettings;
+
+#[derive(Debug, thiserror::Error)]
+pub enum LaunchError {
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+    #[error("invalid bundle: {0}")]
+    Bundle(#[from] BundleError),
+    #[error("could not fetch movie: {0}")]
+    Fetch(#[from] loader::Error),
+    #[error("invalid movie: {0}")]
+    Movie(#[from] tag_utils::Error),
+    #[error("not an SWF file")]
+    NotSwf,
+    #[error("could not create renderer: {0}")]
+    Renderer(#[from] ruffle_render::error::Error),
+    #[error("the player crashed: {0}")]
+    Panicked(String),
+    #[error("unsupported URL scheme {0:?}, expected a path, or an http, https or file URL")]
+    UnsupportedScheme(String),
+    #[error("{0} does not refer to a local file")]
+    InvalidFileUrl(Url),
+}
+
+#[derive(Debug, Clone)]
+pub enum ContentSource {
+    /// An item imported into the library.
+    Library { library: Library, id: Uuid },
+    /// A bare SWF file, opened without importing it.
+    TransientSwf(PathBuf),
+    /// A Ruffle Bundle, zipped or unpacked, opened without importing it.
+    TransientBundle(PathBuf),
+    /// An SWF on the web, opened from a link without importing it.
+    Remote,
+}
+
+#[derive(Debug, Clone)]
+pub struct Launch {
+    pub info: BundleInformation,
+    pub settings: ItemSettings,
+    pub source: ContentSource,
+}
+
+impl Launch {
+    pub fn from_library(library: &Library, item: &LibraryItem) -> Self {
+        Self {
+            info: item.info.clone(),
+            settings: item.settings.clone(),
+            source: ContentSource::Library {
+                library: library.clone(),
+                id: item.id,
+            },
+        }
+    }
+
+    /// Open a file without importing it into the library.
+    ///
+    /// Bundles and SWFs are told apart with [`detect_content_kind`].
+    pub fn transient(path: &Path) -> Result<Self, LaunchError> {
+        let path = path.canonicalize()?;
+        if detect_content_kind(&path) == ContentKind::Bundle {
+            let bundle = Bundle::from_path(&path)?;
+            Ok(Self {
+                info: bundle.information().clone(),
+                settings: ItemSettings::default(),
+                source: ContentSource::TransientBundle(path),
+            })
+        } else {
+            let name = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let url = Url::from_file_path(&path).expect("canonicalized path is absolute");
+            Ok(Self {
+                info: BundleInformation {
+                    name,
+                    url,
+                    player: PlayerOptions::default(),
+                },
+                settings: ItemSettings::default(),
+                source: ContentSource::TransientSwf(path),
+            })
+        }
+    }
+
+    /// Open a path or URL given by the user, e.g. on the command line.
+    ///
+    /// `http` and `https` URLs are loaded remotely, while paths and `file`
+    /// URLs are opened like in [`Launch::transient`], so they may be either
+    /// SWFs or bundles.
+    pub fn from_path_or_url(input: &str) -> Result<Self, LaunchError> {
+        let url = match Url::parse(input) {
+            Ok(url) => url,
+            // Not a URL, so it must be a path
+            Err(_) => return Self::transient(Path::new(input)),
+        };
+        match url.scheme() {
+            "http" | "https" => Ok(Self::remote(url, vec![])),
+            "file" => {
+                // E.g. `file://host/path`, which is on another machine
+                let path = url
+                    .to_file_path()
+                    .map_err(|()| LaunchError::InvalidFileUrl(url.clone()))?;
+                Self::transient(&path)
+            }
+            // Paths may contain colons too
+            _ if Path::new(input).exists() => Self::transient(Path::new(input)),
+            scheme => Err(LaunchError::UnsupportedScheme(scheme.to_string())),
+        }
+    }
+
+    /// Open an SWF on the web without importing it into the library.
+    pub fn remote(url: Url, parameters: Vec<(String, String)>) -> Self {
+        let name = url
+            .path_segments()
+            .and_then(|mut segments| segments.next_back())
+            .and_then(|file_name| Path::new(file_name).file_stem())
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .filter(|name| !name.is_empty())
+            .or_else(|| url.host_str().map(str::to_string))
+            .unwrap_or_default();
+        Self {
+            info: BundleInformation {
+                name,
+                url,
+                player: PlayerOptions {
+                    parameters,
+                    ..Default::default()
+                },
+            },
+            settings: ItemSettings::default(),
+            source: ContentSource::Remote,
+        }
+    }
+
+    /// Whether the content and any data from the session is discarded when
+    /// the player closes.
+    pub fn is_transient(&self) -> bool {
+        !matches!(self.source, ContentSource::Library { .. })
+    }
+
+    /// The path to the file or bundle on disk, if transient.
+    pub fn transient_path(&self) -> Option<&Path> {
+        match &self.source {
+            ContentSource::Library { .. } | ContentSource::Remote => None,
+            ContentSource::TransientSwf(path) | ContentSource::TransientBundle(path) => Some(path),
+        }
+    }
+
+    /// Where the movie's own data is stored.
+    ///
+    /// Transient launches have nowhere to store it, so it's kept in memory
+    /// and discarded when the player closes.
+    pub fn app_data_dir(&self) -> Option<PathBuf> {
+        match &self.source {
+            ContentSource::Library { library, id } => Some(library.app_data_dir(*id)),
+            ContentSource::TransientSwf(_)
+            | ContentSource::TransientBundle(_)
+            | ContentSource::Remote => None,
+        }
+    }
+
+    /// The directories that the movie may open local files from.
+    ///
+    /// Zipped bundles are read through the bundle itself, and remote movies
+    /// have no business reading local files, so they don't get any.
+    pub fn file_sandbox(&self) -> FileSandbox {
+        match &self.source {
+            ContentSource::Library { library, id } => {
+                FileSandbox::new([library.content_dir(*id), library.app_data_dir(*id)])
+            }
+            ContentSource::TransientBundle(path) if path.is_dir() => {
+                FileSandbox::new([path.join(CONTENT_DIR)])
+            }
+            ContentSource::TransientBundle(_) | ContentSource::Remote => FileSandbox::default(),
+            ContentSource::TransientSwf(path) => {
+                FileSandbox::new(path.parent().map(Path::to_path_buf))
+            }
+        }
+    }
+
+    /// Change and store the information and settings of a library item.
+    ///
+    /// Transient launches have nowhere to store them, so this does nothing.
+    pub fn update_item(&self, f: impl FnOnce(&mut LibraryItem)) -> Result<(), LibraryError> {
+        let ContentSource::Library { library, id } = &self.source else {
+            return Ok(());
+        };
+        // Re-read the item, in case it was changed since we launched.
+        let mut item = library.load_item(*id)?;
+        f(&mut item);
+        library.update(&item)
+    }
+
+    pub fn content(&self) -> Result<PlayingContent, LaunchError> {
+        let bundle_path = match &self.source {
+            ContentSource::Library { library, id } => library.bundle_dir(*id),
+            ContentSource::TransientBundle(path) => path.clone(),
+            ContentSource::TransientSwf(_) | ContentSource::Remote => {
+                return Ok(PlayingContent::DirectFile(self.info.url.clone()))
+            }
+        };
+        let bundle = Bundle::from_path(&bundle_path)?;
+        let bundle_url = Url::from_file_path(&bundle_path)
+            .map_err(|()| io::Error::other("bundle path must be absolute"))?;
+        Ok(PlayingContent::Bundle(bundle_url, Box::new(bundle)))
+    }
+}
+
+/// Fetch and parse the root movie.
+///
+/// `on_progress` is called with the number of bytes loaded so far, and the
+/// total number of bytes, if known.
+pub async fn load_root_movie(
+    fetch: OwnedFuture<Box<dyn SuccessResponse>, ErrorResponse>,
+    parameters: Vec<(String, String)>,
+    mut on_progress: impl FnMut(u64, Option<u64>),
+) -> Result<SwfMovie, LaunchError> {
+    let mut response = fetch.await.map_err(|response| response.error)?;
+    let total = response.expected_length().unwrap_or_default();
+    on_progress(0, total);
+
+    let mut data = Vec::new();
+    while let Some(chunk) = response.next_chunk().await? {
+        data.extend_from_slice(&chunk);
+        on_progress(data.len() as u64, total);
+    }
+
+    if !is_swf_header(&data) {
+        return Err(LaunchError::NotSwf);
+    }
+    let mut movie = SwfMovie::from_data(&data, response.url().to_string(), None)?;
+    movie.append_parameters(parameters);
+    Ok(movie)
+}
+
+pub fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "unknown panic".into()
+    }
+}
+
+/// Apply the options to a player that's being built.
+///
+/// Uses the same defaults as desktop Ruffle.
+pub fn apply_player_options(builder: PlayerBuilder, options: &PlayerOptions) -> PlayerBuilder {
+    let spoof_url = options.spoof_url.as_ref().map(|url| url.to_string());
+    builder
+        .with_autoplay(true)
+        .with_letterbox(options.letterbox.unwrap_or(Letterbox::On))
+        .with_max_execution_duration(
+            options
+                .max_execution_duration
+                .unwrap_or(Duration::from_secs(15)),
+        )
+        .with_quality(options.quality.unwrap_or(StageQuality::High))
+        .with_align(
+            options.align.unwrap_or_default(),
+            options.force_align.unwrap_or_default(),
+        )
+        .with_scale_mode(
+            options.scale.unwrap_or_default(),
+            options.force_scale.unwrap_or_default(),
+        )
+        .with_load_behavior(options.load_behavior.unwrap_or(LoadBehavior::Streaming))
+        .with_spoofed_url(spoof_url.clone())
+        .with_page_url(spoof_url)
+        .with_player_version(options.player_version)
+        .with_player_runtime(options.player_runtime.unwrap_or_default())
+        .with_frame_rate(options.frame_rate)
+}
+
+/// Apply the options that can only be set once the player is built.
+pub fn apply_player_options_after_build(player: &mut Player, options: &PlayerOptions) {
+    if options.dummy_external_interface.unwrap_or_default() {
+        player.add_external_interface(Box::new(DummyExternalInterfaceProvider {
+            spoof_url: options.spoof_url.clone(),
+        }));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use ruffle_frontend_utils::bundle::info::BUNDLE_INFORMATION_FILENAME;
+
+    use super::*;
+
+    /// Everything below `dir`, to check that nothing was written.
+    fn list_files(dir: &Path) -> Vec<PathBuf> {
+        let mut files = vec![];
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                files.extend(list_files(&path));
+            }
+            files.push(path);
+        }
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn transient_swf() {
+        let dir = tempfile::tempdir().unwrap();
+        let library = Library::new(dir.path().join("library"));
+        let path = dir.path().join("movie.swf");
+        fs::write(&path, b"FWS\x0amovie").unwrap();
+        let files = list_files(dir.path());
+
+        let launch = Launch::transient(&path).unwrap();
+        assert_eq!(launch.info.name, "movie");
+        assert!(launch.is_transient());
+        assert_eq!(launch.app_data_dir(), None);
+        let url = Url::from_file_path(path.canonicalize().unwrap()).unwrap();
+        assert!(matches!(
+            launch.content().unwrap(),
+            PlayingContent::DirectFile(content_url) if content_url == url
+        ));
+
+        assert!(!library.root().exists());
+        assert_eq!(list_files(dir.path()), files);
+    }
+
+    #[test]
+    fn transient_bundle() {
+        let dir = tempfile::tempdir().unwrap();
+        let library = Library::new(dir.path().join("library"));
+        let path = dir.path().join("bundle");
+        fs::create_dir_all(path.join(CONTENT_DIR)).unwrap();
+        fs::write(path.join(CONTENT_DIR).join("movie.swf"), b"FWS\x0amovie").unwrap();
+        fs::write(
+            path.join(BUNDLE_INFORMATION_FILENAME),
+            "[bundle]\nname = \"Movie\"\nurl = \"file:///movie.swf\"\n",
+        )
+        .unwrap();
+        let files = list_files(dir.path());
+
+        let launch = Launch::transient(&path).unwrap();
+        assert_eq!(launch.info.name, "Movie");
+        assert!(launch.is_transient());
+        assert_eq!(launch.app_data_dir(), None);
+        let url = Url::from_file_path(path.canonicalize().unwrap()).unwrap();
+        assert!(matches!(
+            launch.content().unwrap(),
+            PlayingContent::Bundle(bundle_url, _) if bundle_url == url
+        ));
+
+        assert!(!library.root().exists());
+        assert_eq!(list_files(dir.path()), files);
+    }
+
+    #[test]
+    fn update_item_transient() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("movie.swf");
+        fs::write(&path, b"FWS\x0amovie").unwrap();
+        let files = list_files(dir.path());
+
+        let url = Url::parse("https://example.com/movie.swf").unwrap();
+        for launch in [
+            Launch::transient(&path).unwrap(),
+            Launch::remote(url, vec![]),
+        ] {
+            launch
+                .update_item(|_| panic!("transient launches have no item"))
+                .unwrap();
+        }
+        assert_eq!(list_files(dir.path()), files);
+    }
+
+    #[test]
+    fn from_path_or_url() {
+        let launch = Launch::from_path_or_url("https://example.com/movie.swf").unwrap();
+        assert!(matches!(launch.source, ContentSource::Remote));
+        assert_eq!(launch.info.name, "movie");
+
+        assert!(matches!(
+            Launch::from_path_or_url("file://example.com/movie.swf"),
+            Err(LaunchError::InvalidFileUrl(url)) if url.host_str() == Some("example.com")
+        ));
+        assert!(matches!(
+            Launch::from_path_or_url("ftp://example.com/movie.swf"),
+            Err(LaunchError::UnsupportedScheme(scheme)) if scheme == "ftp"
+        ));
+    }
+}
@@ -1,19 +1,57 @@
+//! The app itself only runs on iOS, while launching and running movies is
+//! portable, so that movies can also be run headless, e.g. on Linux.
+#[cfg(target_os = "ios")]
 use std::ptr::NonNull;
 
+#[cfg(target_os = "ios")]
 use objc2::runtime::AnyClass;
+#[cfg(target_os = "ios")]
 use objc2::ClassType;
+#[cfg(target_os = "ios")]
 use objc2_foundation::{MainThreadMarker, NSStringFromClass};
+#[cfg(target_os = "ios")]
 use objc2_ui_kit::UIApplicationMain;
 
+#[cfg(target_os = "ios")]
+mod actions;
+#[cfg(target_os = "ios")]
 mod app_delegate;
+pub mod batch;
+pub mod cli;
+pub mod console;
+#[cfg(target_os = "ios")]
+mod console_controller;
+pub mod deep_link;
+#[cfg(target_os = "ios")]
 mod edit_controller;
+#[cfg(target_os = "ios")]
+mod executor;
+mod external_interface;
+pub mod headless;
+pub mod import;
+pub mod launch;
+pub mod library;
+#[cfg(target_os = "ios")]
 mod library_controller;
+#[cfg(target_os = "ios")]
 mod player_controller;
+#[cfg(target_os = "ios")]
 mod player_view;
+#[cfg(target_os = "ios")]
+mod preferences;
+pub mod sandbox;
+#[cfg(target_os = "ios")]
 mod scene_delegate;
+pub mod scheduler;
+pub mod settings;
+pub mod storage;
 
+#[cfg(target_os = "ios")]
 pub use self::app_delegate::AppDelegate;
+pub use self::launch::Launch;
+#[cfg(target_os = "ios")]
 pub use self::player_controller::PlayerController;
+#[cfg(target_os = "ios")]
 pub use self::player_view::PlayerView;
 
 pub fn init_logging() {
@@ -21,20 +59,20 @@ pub fn init_logging() {
     // Catalyst or native.
     // TODO: If running Mac Catalyst under Xcode
     let filter = log::LevelFilter::Info;
-    if cfg!(target_abi = "macabi") {
-        simple_logger::SimpleLogger::new()
-            .with_level(filter)
-            .env()
-            .init()
-            .unwrap();
-    } else {
-        oslog::OsLogger::new(module_path!())
-            .level_filter(filter)
-            .init()
-            .unwrap();
-    }
+    #[cfg(all(target_os = "ios", not(target_abi = "macabi")))]
+    oslog::OsLogger::new(module_path!())
+        .level_filter(filter)
+        .init()
+        .unwrap();
+    #[cfg(not(all(target_os = "ios", not(target_abi = "macabi"))))]
+    simple_logger::SimpleLogger::new()
+        .with_level(filter)
+        .env()
+        .init()
+        .unwrap();
 }
 
+#[cfg(target_os = "ios")]
 pub fn launch(app_class: Option<&AnyClass>, delegate_class: Option<&AnyClass>) {
     // Set inside Info.plist
     let _ = scene_delegate::SceneDelegate::class();
@@ -48,6 +86,15 @@ pub fn launch(app_class: Option<&AnyClass>, delegate_class: Option<&AnyClass>) {
     let _ = edit_controller::EditController::class();
 
     let _ = MainThreadMarker::new().unwrap();
+
+    // Network I/O is driven by the runtime's worker threads, while the
+    // futures themselves are polled on the main thread, see `executor`.
+    //
+    // `UIApplicationMain` never returns, so the runtime lives for the rest of
+    // the program.
+    let runtime = tokio::runtime::Runtime::new().expect("failed creating Tokio runtime");
+    let _guard = runtime.enter();
+
     unsafe {
         UIApplicationMain(
             *libc::_NSGetArgc(),
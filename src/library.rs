@@ -0,0 +1,338 @@
+//! The on-disk library of imported bundles.
+//!
+//! Each item is stored in its own directory, named by a random UUID to avoid
+//! conflicts between bundles with the same name:
+//!
+//! ```text
+//! library/
+//! └── $uuid/
+//!     ├── bundle.ruf/     The unpacked Ruffle Bundle.
+//!     ├── settings.toml   User settings, overrides the bundle's information.
+//!     └── app_data/       Data stored by the movie itself.
+//! ```
+//!
+//! This intentionally doesn't depend on UIKit.
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use ruffle_frontend_utils::bundle::info::{
+    BundleInformation, BundleInformationParseError, BUNDLE_INFORMATION_FILENAME,
+};
+use ruffle_frontend_utils::bundle::{Bundle, BundleError};
+use uuid::Uuid;
+
+use crate::settings::{
+    read_settings, update_settings, write_bundle_information, write_settings, ItemSettings,
+};
+
+const BUNDLE_DIR: &str = "bundle.ruf";
+/// The directory in a bundle that `file:///` URLs resolve to.
+pub const CONTENT_DIR: &str = "content";
+const SETTINGS_FILE: &str = "settings.toml";
+const APP_DATA_DIR: &str = "app_data";
+
+#[derive(Debug, thiserror::Error)]
+pub enum LibraryError {
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+    #[error("invalid bundle: {0}")]
+    Bundle(#[from] BundleError),
+    #[error("invalid settings: {0}")]
+    Settings(#[from] BundleInformationParseError),
+    #[error("no library item with id {0}")]
+    NotFound(Uuid),
+}
+
+#[derive(Debug, Clone)]
+pub struct LibraryItem {
+    pub id: Uuid,
+    pub info: BundleInformation,
+    pub settings: ItemSettings,
+}
+
+#[derive(Debug, Clone)]
+pub struct Library {
+    root: PathBuf,
+}
+
+impl Library {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// The library inside the application's data directory.
+    pub fn default_root() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("library"))
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn item_dir(&self, id: Uuid) -> PathBuf {
+        self.root.join(id.as_hyphenated().to_string())
+    }
+
+    pub fn bundle_dir(&self, id: Uuid) -> PathBuf {
+        self.item_dir(id).join(BUNDLE_DIR)
+    }
+
+    /// The directory that `file:///` URLs in the bundle resolve to.
+    pub fn content_dir(&self, id: Uuid) -> PathBuf {
+        self.bundle_dir(id).join(CONTENT_DIR)
+    }
+
+    pub fn settings_path(&self, id: Uuid) -> PathBuf {
+        self.item_dir(id).join(SETTINGS_FILE)
+    }
+
+    pub fn app_data_dir(&self, id: Uuid) -> PathBuf {
+        self.item_dir(id).join(APP_DATA_DIR)
+    }
+
+    /// Load all items in the library, sorted by name.
+    ///
+    /// Items that fail to load are skipped, so that a single broken item
+    /// doesn't make the entire library unavailable.
+    pub fn load(&self) -> Result<Vec<LibraryItem>, LibraryError> {
+        let entries = match fs::read_dir(&self.root) {
+            Ok(entries) => entries,
+            // The library is created lazily on first insert
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut items = vec![];
+        for entry in entries {
+            let entry = entry?;
+            let file_name = entry.file_name();
+            let Some(id) = file_name.to_str().and_then(|s| Uuid::try_parse(s).ok()) else {
+                tracing::warn!(?file_name, "unknown entry in library");
+                continue;
+            };
+            match self.load_item(id) {
+                Ok(item) => items.push(item),
+                Err(err) => tracing::error!(%id, "failed loading library item: {err}"),
+            }
+        }
+
+        items.sort_by(|a, b| a.info.name.cmp(&b.info.name));
+        Ok(items)
+    }
+
+    pub fn load_item(&self, id: Uuid) -> Result<LibraryItem, LibraryError> {
+        let item_dir = self.item_dir(id);
+        if !item_dir.is_dir() {
+            return Err(LibraryError::NotFound(id));
+        }
+
+        // User settings take precedence over the bundle's own information.
+        let (info, settings) = match fs::read_to_string(self.settings_path(id)) {
+            Ok(settings) => read_settings(&settings)?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                let bundle = Bundle::from_path(self.bundle_dir(id))?;
+                (bundle.information().clone(), ItemSettings::default())
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(LibraryItem { id, info, settings })
+    }
+
+    /// Create a new item with an empty bundle described by `info`.
+    ///
+    /// The bundle's content directory is created, but left empty, it is up to
+    /// the caller to populate it.
+    pub fn insert(&self, info: BundleInformation) -> Result<LibraryItem, LibraryError> {
+        self.insert_with(|bundle_dir| {
+            fs::create_dir_all(bundle_dir.join(CONTENT_DIR))?;
+            fs::write(
+                bundle_dir.join(BUNDLE_INFORMATION_FILENAME),
+                write_bundle_information(&info),
+            )?;
+            Ok::<_, LibraryError>(())
+        })
+    }
+
+    /// Create a new item, and let `populate` fill out the bundle directory.
+    ///
+    /// The item is removed again if populating or loading it fails, so that
+    /// we don't leave half-created items around.
+    pub fn insert_with<E: From<LibraryError>>(
+        &self,
+        populate: impl FnOnce(&Path) -> Result<(), E>,
+    ) -> Result<LibraryItem, E> {
+        let id = Uuid::new_v4();
+
+        let res = (|| -> Result<LibraryItem, E> {
+            fs::create_dir_all(self.app_data_dir(id)).map_err(LibraryError::from)?;
+            populate(&self.bundle_dir(id))?;
+            Ok(self.load_item(id)?)
+        })();
+        if res.is_err() {
+            if let Err(err) = fs::remove_dir_all(self.item_dir(id)) {
+                tracing::error!(%id, "failed cleaning up library item: {err}");
+            }
+        }
+        res
+    }
+
+    /// Store the user's changes to an item.
+    ///
+    /// This updates `settings.toml`, the bundle itself is left untouched.
+    pub fn update(&self, item: &LibraryItem) -> Result<(), LibraryError> {
+        if !self.item_dir(item.id).is_dir() {
+            return Err(LibraryError::NotFound(item.id));
+        }
+        // Write to a temporary file first, to avoid corrupting the settings
+        // if we're interrupted.
+        let path = self.settings_path(item.id);
+        let tmp_path = path.with_extension("toml.tmp");
+        // Keep whatever we don't understand in the existing settings.
+        let contents = match fs::read_to_string(&path) {
+            Ok(existing) => update_settings(&existing, &item.info, &item.settings),
+            Err(err) => {
+                tracing::warn!(id = %item.id, "rewriting unreadable settings: {err}");
+                write_settings(&item.info, &item.settings)
+            }
+        };
+        fs::write(&tmp_path, contents)?;
+        fs::rename(&tmp_path, &path)?;
+        Ok(())
+    }
+
+    /// Remove an item, including its settings and application data.
+    pub fn delete(&self, id: Uuid) -> Result<(), LibraryError> {
+        match fs::remove_dir_all(self.item_dir(id)) {
+            Ok(()) => Ok(()),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Err(LibraryError::NotFound(id)),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ruffle_core::backend::navigator::OpenURLMode;
+    use ruffle_frontend_utils::player_options::PlayerOptions;
+    use tempfile::TempDir;
+    use url::Url;
+
+    use super::*;
+
+    fn info(name: &str) -> BundleInformation {
+        BundleInformation {
+            name: name.into(),
+            url: Url::parse("file:///movie.swf").unwrap(),
+            player: PlayerOptions::default(),
+        }
+    }
+
+    fn library() -> (TempDir, Library) {
+        let dir = tempfile::tempdir().unwrap();
+        let library = Library::new(dir.path().join("library"));
+        (dir, library)
+    }
+
+    #[test]
+    fn load_missing_root() {
+        let (_dir, library) = library();
+        assert!(library.load().unwrap().is_empty());
+    }
+
+    #[test]
+    fn insert_and_load() {
+        let (_dir, library) = library();
+        let b = library.insert(info("b")).unwrap();
+        let a = library.insert(info("a")).unwrap();
+        assert_eq!(b.info.name, "b");
+        assert_eq!(b.settings, ItemSettings::default());
+        assert!(library.content_dir(b.id).is_dir());
+        assert!(library.app_data_dir(b.id).is_dir());
+
+        // Unknown entries are skipped
+        fs::create_dir(library.root().join("not-a-uuid")).unwrap();
+
+        let items = library.load().unwrap();
+        let ids: Vec<_> = items.iter().map(|item| item.id).collect();
+        assert_eq!(ids, [a.id, b.id], "sorted by name");
+    }
+
+    #[test]
+    fn load_skips_broken_items() {
+        let (_dir, library) = library();
+        let item = library.insert(info("movie")).unwrap();
+        let broken = library.insert(info("broken")).unwrap();
+        fs::write(library.settings_path(broken.id), "not = [valid").unwrap();
+
+        let items = library.load().unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].id, item.id);
+        assert!(matches!(
+            library.load_item(broken.id),
+            Err(LibraryError::Settings(_))
+        ));
+    }
+
+    #[test]
+    fn failed_insert_is_cleaned_up() {
+        let (_dir, library) = library();
+        let res = library.insert_with(|_| Err(LibraryError::Io(io::ErrorKind::Other.into())));
+        assert!(res.is_err());
+        assert_eq!(fs::read_dir(library.root()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn update() {
+        let (_dir, library) = library();
+        let mut item = library.insert(info("before")).unwrap();
+        item.info.name = "after".into();
+        item.settings.open_url_mode = Some(OpenURLMode::Deny);
+        item.settings.socket_allowed.push("example.com:80".into());
+        library.update(&item).unwrap();
+
+        // Written through a temporary file, which is renamed into place
+        let settings_path = library.settings_path(item.id);
+        assert!(settings_path.is_file());
+        assert!(!settings_path.with_extension("toml.tmp").exists());
+
+        let loaded = library.load_item(item.id).unwrap();
+        assert_eq!(loaded.info.name, "after");
+        assert_eq!(loaded.settings, item.settings);
+
+        // Updating again replaces the existing settings
+        item.info.name = "again".into();
+        library.update(&item).unwrap();
+        assert_eq!(library.load_item(item.id).unwrap().info.name, "again");
+    }
+
+    #[test]
+    fn update_missing() {
+        let (_dir, library) = library();
+        let item = library.insert(info("movie")).unwrap();
+        library.delete(item.id).unwrap();
+        assert!(matches!(
+            library.update(&item),
+            Err(LibraryError::NotFound(id)) if id == item.id
+        ));
+        assert!(!library.item_dir(item.id).exists());
+    }
+
+    #[test]
+    fn delete() {
+        let (_dir, library) = library();
+        let item = library.insert(info("movie")).unwrap();
+        library.delete(item.id).unwrap();
+        assert!(!library.item_dir(item.id).exists());
+        assert!(library.load().unwrap().is_empty());
+        assert!(matches!(
+            library.load_item(item.id),
+            Err(LibraryError::NotFound(_))
+        ));
+        assert!(matches!(
+            library.delete(item.id),
+            Err(LibraryError::NotFound(_))
+        ));
+    }
+}
@@ -1,30 +1,38 @@
-use std::cell::{OnceCell, RefCell};
+use std::cell::{Cell, OnceCell, RefCell};
 
 use objc2::rc::{Allocated, Retained};
 use objc2::{declare_class, msg_send, msg_send_id, mutability, ClassType, DeclaredClass};
 use objc2_foundation::{
-    ns_string, NSBundle, NSCoder, NSIndexPath, NSInteger, NSObject, NSObjectProtocol, NSString,
+    ns_string, NSArray, NSBundle, NSCoder, NSIndexPath, NSInteger, NSObject, NSObjectProtocol,
+    NSString,
 };
 #[allow(deprecated)]
 use objc2_ui_kit::UIStoryboardSegue;
 use objc2_ui_kit::{
-    NSDataAsset, NSIndexPathUIKitAdditions, UIBarButtonItem, UILabel, UITableView, UITableViewCell,
-    UITableViewController, UITableViewDataSource,
+    NSDataAsset, NSIndexPathUIKitAdditions, UIBarButtonItem, UILabel, UINavigationController,
+    UITableView, UITableViewCell, UITableViewCellEditingStyle, UITableViewController,
+    UITableViewDataSource, UITableViewRowAnimation,
 };
 use ruffle_core::tag_utils::SwfMovie;
 use ruffle_core::PlayerBuilder;
 use ruffle_frontend_utils::backends::audio::CpalAudioBackend;
 use ruffle_frontend_utils::bundle::info::BundleInformation;
 use ruffle_frontend_utils::player_options::PlayerOptions;
-use url::Url;
+use uuid::Uuid;
 
-use crate::edit_controller::{Action, EditController};
+use crate::edit_controller::{placeholder_url, Action, EditController};
+use crate::launch::Launch;
+use crate::library::{Library, LibraryItem};
+use crate::settings::ItemSettings;
 use crate::{PlayerController, PlayerView};
 
 #[derive(Default)]
 pub struct Ivars {
     logo_view: OnceCell<Retained<PlayerView>>,
-    bundles: RefCell<Vec<BundleInformation>>,
+    library: OnceCell<Library>,
+    items: RefCell<Vec<LibraryItem>>,
+    /// The item currently being edited.
+    editing: Cell<Option<Uuid>>,
 }
 
 declare_class!(
@@ -97,6 +105,15 @@ declare_class!(
         fn _prepare_for_segue(&self, segue: &UIStoryboardSegue, sender: Option<&NSObject>) {
             self.prepare_for_segue(segue, sender.expect("has sender"));
         }
+
+        #[method(shouldPerformSegueWithIdentifier:sender:)]
+        fn _should_perform_segue(
+            &self,
+            _identifier: Option<&NSString>,
+            sender: Option<&NSObject>,
+        ) -> bool {
+            self.should_perform_segue(sender)
+        }
     }
 
     // Storyboard
@@ -128,7 +145,9 @@ declare_class!(
 
         #[method(cancelEditItem:)]
         #[allow(deprecated)]
-        fn _cancel_edit_item(&self, _segue: &UIStoryboardSegue) {}
+        fn _cancel_edit_item(&self, _segue: &UIStoryboardSegue) {
+            self.ivars().editing.set(None);
+        }
 
         #[method(saveEditItem:)]
         #[allow(deprecated)]
@@ -145,7 +164,7 @@ declare_class!(
             _table_view: &UITableView,
             _section: NSInteger,
         ) -> NSInteger {
-            self.ivars().bundles.borrow().len() as NSInteger
+            self.ivars().items.borrow().len() as NSInteger
         }
 
         #[method(numberOfSectionsInTableView:)]
@@ -170,6 +189,18 @@ declare_class!(
         ) -> Option<Retained<NSString>> {
             Some(NSString::from_str("Library"))
         }
+
+        #[method(tableView:commitEditingStyle:forRowAtIndexPath:)]
+        fn tableView_commitEditingStyle_forRowAtIndexPath(
+            &self,
+            table_view: &UITableView,
+            editing_style: UITableViewCellEditingStyle,
+            index_path: &NSIndexPath,
+        ) {
+            if editing_style == UITableViewCellEditingStyle::Delete {
+                self.delete_item(table_view, index_path);
+            }
+        }
     }
 );
 
@@ -182,18 +213,40 @@ impl LibraryController {
         tracing::info!("library viewDidLoad");
 
         self.setup_logo();
+        self.reload();
+    }
 
-        let mut bundles = self.ivars().bundles.borrow_mut();
-        bundles.push(BundleInformation {
-            name: "Example SWF".into(),
-            url: Url::parse("file:///example.swf").unwrap(),
-            player: PlayerOptions::default(),
-        });
-        bundles.push(BundleInformation {
-            name: "Another example".into(),
-            url: Url::parse("file:///example2.swf").unwrap(),
-            player: PlayerOptions::default(),
-        });
+    /// Set the library to show, passed down from the scene delegate.
+    pub fn set_library(&self, library: Library) {
+        if self.ivars().library.set(library).is_err() {
+            tracing::warn!("library already set");
+            return;
+        }
+        self.reload();
+    }
+
+    /// Reload the items from disk, e.g. after an import.
+    pub fn reload(&self) {
+        // Otherwise the items are loaded once the library is set
+        let Some(library) = self.ivars().library.get() else {
+            return;
+        };
+        match library.load() {
+            Ok(items) => *self.ivars().items.borrow_mut() = items,
+            Err(err) => tracing::error!("failed loading library: {err}"),
+        }
+        if let Some(table_view) = unsafe { self.tableView() } {
+            unsafe { table_view.reloadData() };
+        }
+    }
+
+    /// Items only exist once the library is set, so everything that acts on
+    /// an item can rely on it.
+    fn library(&self) -> &Library {
+        self.ivars()
+            .library
+            .get()
+            .expect("library set before acting on items")
     }
 
     fn setup_logo(&self) {
@@ -205,7 +258,14 @@ impl LibraryController {
         let movie = SwfMovie::from_data(data.bytes(), "file://logo-anim.swf".into(), None)
             .expect("loading movie");
 
-        let renderer = view.create_renderer();
+        // The logo is just decoration, so don't bother the user if it fails.
+        let renderer = match view.create_renderer() {
+            Ok(renderer) => renderer,
+            Err(err) => {
+                tracing::error!("failed creating renderer for logo: {err}");
+                return;
+            }
+        };
 
         let mut builder = PlayerBuilder::new()
             .with_renderer(renderer)
@@ -219,7 +279,7 @@ impl LibraryController {
         view.set_player(builder.build());
         // HACK: Skip first frame to avoid a flicker on startup
         // FIXME: This probably indicates a bug in our timing code?
-        view.player_lock().run_frame();
+        view.with_player(|player| player.run_frame());
     }
 
     fn view_is_appearing(&self, _animated: bool) {
@@ -237,7 +297,8 @@ impl LibraryController {
     fn view_did_disappear(&self, _animated: bool) {
         tracing::info!("library viewDidDisappear:");
 
-        self.logo_view().player_lock().flush_shared_objects();
+        self.logo_view()
+            .with_player(|player| player.flush_shared_objects());
     }
 
     #[allow(deprecated)]
@@ -255,9 +316,10 @@ impl LibraryController {
                 Action::New,
                 BundleInformation {
                     name: "".into(),
-                    url: Url::parse("file://").unwrap(),
+                    url: placeholder_url(),
                     player: PlayerOptions::default(),
                 },
+                ItemSettings::default(),
             );
         } else if &*identifier == ns_string!("edit-item") {
             assert!(destination.isKindOfClass(EditController::class()));
@@ -265,24 +327,24 @@ impl LibraryController {
             assert!(sender.isKindOfClass(UITableViewCell::class()));
             let cell = unsafe { &*(sender as *const NSObject as *const UITableViewCell) };
 
-            // TODO
-            edit_controller.configure(
-                Action::Edit,
-                BundleInformation {
-                    name: "".into(),
-                    url: Url::parse("file://").unwrap(),
-                    player: PlayerOptions::default(),
-                },
-            );
-            dbg!(cell);
+            let Some(item) = self.item_for_cell(cell) else {
+                tracing::error!("no library item to edit");
+                return;
+            };
+            self.ivars().editing.set(Some(item.id));
+            edit_controller.configure(Action::Edit, item.info, item.settings);
         } else if &*identifier == ns_string!("run-item") {
             assert!(destination.isKindOfClass(PlayerController::class()));
             let player_controller = unsafe { Retained::cast::<PlayerController>(destination) };
             assert!(sender.isKindOfClass(UITableViewCell::class()));
             let cell = unsafe { &*(sender as *const NSObject as *const UITableViewCell) };
 
-            // TODO
-            dbg!(cell, player_controller);
+            let Some(item) = self.item_for_cell(cell) else {
+                tracing::error!("no library item to run");
+                return;
+            };
+            player_controller.set_library(self.library().clone());
+            player_controller.configure(Launch::from_library(self.library(), &item));
         } else {
             unreachable!("unknown identifier for segue: {identifier:?}")
         }
@@ -294,7 +356,58 @@ impl LibraryController {
         let edit_controller = unsafe { segue.sourceViewController() };
         assert!(edit_controller.isKindOfClass(EditController::class()));
         let edit_controller = unsafe { Retained::cast::<EditController>(edit_controller) };
-        dbg!(edit_controller); // TODO
+
+        edit_controller.end_editing();
+        let Some(library) = self.ivars().library.get() else {
+            tracing::error!("no library to save the item in");
+            return;
+        };
+        let info = edit_controller.info();
+        let settings = edit_controller.settings();
+        let res = match edit_controller.action() {
+            Action::New => library.insert(info).and_then(|item| {
+                if settings == ItemSettings::default() {
+                    return Ok(());
+                }
+                library.update(&LibraryItem { settings, ..item })
+            }),
+            Action::Edit => {
+                let id = self.ivars().editing.take().expect("editing an item");
+                library.load_item(id).and_then(|item| {
+                    library.update(&LibraryItem {
+                        info,
+                        settings,
+                        ..item
+                    })
+                })
+            }
+        };
+        if let Err(err) = res {
+            tracing::error!("failed saving library item: {err}");
+        }
+        self.reload();
+    }
+
+    /// Segues from a cell need its item, which may be gone if the cell was
+    /// just deleted, or the library reloaded.
+    fn should_perform_segue(&self, sender: Option<&NSObject>) -> bool {
+        let Some(sender) = sender.filter(|sender| sender.isKindOfClass(UITableViewCell::class()))
+        else {
+            return true;
+        };
+        let cell = unsafe { &*(sender as *const NSObject as *const UITableViewCell) };
+        let has_item = self.item_for_cell(cell).is_some();
+        if !has_item {
+            tracing::warn!("ignoring segue from a cell without a library item");
+        }
+        has_item
+    }
+
+    fn item_for_cell(&self, cell: &UITableViewCell) -> Option<LibraryItem> {
+        let table_view = unsafe { self.tableView() }.expect("has table view");
+        let index_path = unsafe { table_view.indexPathForCell(cell) }?;
+        let row = unsafe { index_path.row() } as usize;
+        self.ivars().items.borrow().get(row).cloned()
     }
 
     fn toggle_editing(&self, button: &UIBarButtonItem) {
@@ -310,12 +423,32 @@ impl LibraryController {
         }
     }
 
+    fn delete_item(&self, table_view: &UITableView, index_path: &NSIndexPath) {
+        let row = unsafe { index_path.row() } as usize;
+        let Some(id) = self.ivars().items.borrow().get(row).map(|item| item.id) else {
+            tracing::warn!(row, "no library item to delete");
+            return;
+        };
+        if let Err(err) = self.library().delete(id) {
+            tracing::error!(%id, "failed deleting library item: {err}");
+            return;
+        }
+        self.ivars().items.borrow_mut().remove(row);
+        unsafe {
+            table_view.deleteRowsAtIndexPaths_withRowAnimation(
+                &NSArray::from_slice(&[index_path]),
+                UITableViewRowAnimation::Automatic,
+            );
+        }
+    }
+
     fn cell_at(
         &self,
         table_view: &UITableView,
         index_path: &NSIndexPath,
     ) -> Retained<UITableViewCell> {
-        let bundle = &self.ivars().bundles.borrow()[unsafe { index_path.row() } as usize];
+        let items = self.ivars().items.borrow();
+        let bundle = &items[unsafe { index_path.row() } as usize].info;
 
         unsafe {
             let cell = table_view.dequeueReusableCellWithIdentifier_forIndexPath(
@@ -334,3 +467,21 @@ impl LibraryController {
         }
     }
 }
+
+/// The library controllers shown in the navigation controller.
+pub fn library_controllers(
+    navigation_controller: &UINavigationController,
+) -> impl Iterator<Item = Retained<LibraryController>> {
+    unsafe { navigation_controller.viewControllers() }
+        .into_iter()
+        .filter(|controller| controller.isKindOfClass(LibraryController::class()))
+        .map(|controller| unsafe { Retained::cast::<LibraryController>(controller) })
+}
+
+/// Reload the library shown in the navigation controller, e.g. after an
+/// import from elsewhere.
+pub fn reload_library(navigation_controller: &UINavigationController) {
+    for library_controller in library_controllers(navigation_controller) {
+        library_controller.reload();
+    }
+}
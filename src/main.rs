@@ -1,8 +1,14 @@
-use objc2::ClassType;
-use ruffle_ios::{init_logging, launch, AppDelegate};
+#[cfg(target_os = "ios")]
+fn main() {
+    use objc2::ClassType;
+    use ruffle_ios::{init_logging, launch, AppDelegate};
 
-#[tokio::main]
-async fn main() {
     init_logging();
     launch(None, Some(AppDelegate::class()));
 }
+
+#[cfg(not(target_os = "ios"))]
+fn main() {
+    eprintln!("the app only runs on iOS, use `run_swf --headless` to run movies elsewhere");
+    std::process::exit(1);
+}
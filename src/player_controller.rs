@@ -1,58 +1,164 @@
-use std::cell::OnceCell;
+use std::cell::{Cell, OnceCell, RefCell};
+use std::collections::HashMap;
 use std::fs::File;
+use std::panic::{self, AssertUnwindSafe};
 use std::path::Path;
+use std::ptr::NonNull;
 use std::rc::Rc;
-use std::sync::{Arc, Mutex, MutexGuard};
+use std::sync::{Arc, Mutex};
 use std::{io, ptr};
 
-use objc2::rc::{Allocated, Retained};
+use block2::{Block, RcBlock};
+use objc2::rc::{Allocated, Retained, Weak};
 use objc2::runtime::AnyObject;
 use objc2::{declare_class, msg_send, msg_send_id, mutability, ClassType, DeclaredClass};
 use objc2_foundation::{
-    ns_string, CGPoint, CGRect, CGSize, MainThreadMarker, NSBundle, NSCoder, NSObjectProtocol,
-    NSString,
+    ns_string, CGPoint, CGRect, CGSize, MainThreadBound, MainThreadMarker, NSArray, NSBundle,
+    NSCoder, NSObjectProtocol, NSString,
 };
-use objc2_ui_kit::{NSDataAsset, UIViewController};
+use objc2_ui_kit::{
+    NSDataAsset, NSTextAlignment, UIAction, UIAlertAction, UIAlertActionStyle, UIAlertController,
+    UIAlertControllerStyle, UIApplication, UIBarButtonItem, UIBarButtonSystemItem, UIButton,
+    UIButtonType, UIColor, UIControlEvents, UIControlState, UIImage, UILabel, UIMenu,
+    UIMenuElementAttributes, UIMenuElementState, UIMenuOptions, UINavigationController,
+    UIProgressView, UIProgressViewStyle, UIView, UIViewController,
+};
+use ruffle_core::backend::navigator::{NavigatorBackend, OpenURLMode, Request, SocketMode};
 use ruffle_core::config::Letterbox;
 use ruffle_core::tag_utils::SwfMovie;
-use ruffle_core::{Player, PlayerBuilder};
+use ruffle_core::{Player, PlayerBuilder, StageScaleMode};
 use ruffle_frontend_utils::backends::audio::CpalAudioBackend;
-use ruffle_frontend_utils::backends::executor::{AsyncExecutor, PollRequester};
+use ruffle_frontend_utils::backends::executor::AsyncExecutor;
 use ruffle_frontend_utils::backends::navigator::{ExternalNavigatorBackend, NavigatorInterface};
+use ruffle_frontend_utils::bundle::info::BundleInformation;
 use ruffle_frontend_utils::content::PlayingContent;
+use ruffle_frontend_utils::player_options::PlayerOptions;
+use ruffle_render::quality::StageQuality;
+use tokio::sync::oneshot;
 use url::Url;
 
+use crate::actions::{action, bar_button_item, image_bar_button_item};
+use crate::console::{ConsoleBuffer, ConsoleLogBackend};
+use crate::console_controller::ConsoleController;
+use crate::executor::{Executor, MainThreadPoller};
+use crate::import::{import_ruf, import_swf, ImportError};
+use crate::launch::{
+    apply_player_options, apply_player_options_after_build, load_root_movie, panic_message,
+    ContentSource, Launch, LaunchError,
+};
+use crate::library::Library;
+use crate::library_controller::reload_library;
 use crate::player_view::PlayerView;
+use crate::preferences::sync_rendering_to_display;
+use crate::sandbox::FileSandbox;
+use crate::settings::ViewportLayout;
+use crate::storage::DiskStorageBackend;
 
-#[derive(Clone)]
-pub struct EventSender(Rc<OnceCell<Arc<AsyncExecutor<EventSender>>>>);
-
-impl PollRequester for EventSender {
-    fn request_poll(&self) {
-        eprintln!("request_poll, main: {}", MainThreadMarker::new().is_some());
-        self.0.get().expect("initialized").poll_all();
-    }
+/// Options that can be changed while the movie is running.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct DisplayOptions {
+    scale: StageScaleMode,
+    letterbox: Letterbox,
+    quality: StageQuality,
 }
 
+// TODO: Localization
+const SCALE_MODES: &[(&str, StageScaleMode)] = &[
+    ("Unscaled (100%)", StageScaleMode::NoScale),
+    ("Zoom to Fit", StageScaleMode::ShowAll),
+    ("Stretch to Fit", StageScaleMode::ExactFit),
+    ("Crop to Fit", StageScaleMode::NoBorder),
+];
+
+const QUALITIES: &[(&str, StageQuality)] = &[
+    ("Low", StageQuality::Low),
+    ("Medium", StageQuality::Medium),
+    ("High", StageQuality::High),
+    ("Best", StageQuality::Best),
+    ("High (8x8)", StageQuality::High8x8),
+    ("High (8x8) Linear", StageQuality::High8x8Linear),
+    ("High (16x16)", StageQuality::High16x16),
+    ("High (16x16) Linear", StageQuality::High16x16Linear),
+];
+
 #[derive(Default)]
 pub struct Ivars {
-    movie_path: Option<String>,
-    player: OnceCell<Arc<Mutex<Player>>>,
-    executor: OnceCell<Arc<AsyncExecutor<EventSender>>>,
+    launch: OnceCell<Launch>,
+    /// Where transient movies are offered to be imported into, if anywhere.
+    library: OnceCell<Library>,
+    /// Called once the player is closed, and the import offer is answered.
+    close_handler: RefCell<Option<Box<dyn FnOnce()>>>,
+    /// Set when another movie replaces this player, since the import offer
+    /// would clash with presenting the new player.
+    replaced: Cell<bool>,
+    player_view: OnceCell<Retained<PlayerView>>,
+    progress_view: OnceCell<Retained<UIProgressView>>,
+    status_label: OnceCell<Retained<UILabel>>,
+    retry_button: OnceCell<Retained<UIButton>>,
+    back_button: OnceCell<Retained<UIButton>>,
+    /// Incremented each time the player is (re)started, so that results from
+    /// a previous attempt are ignored.
+    session: Cell<u32>,
+    /// Whether the user paused playback.
+    paused: Cell<bool>,
+    /// Whether the scene is inactive.
+    suspended: Cell<bool>,
+    display_item: OnceCell<Retained<UIBarButtonItem>>,
+    /// Display options chosen by the user in this session, kept when
+    /// restarting.
+    display_options: Cell<Option<DisplayOptions>>,
+    /// Where on the screen the movie is shown.
+    layout: Cell<ViewportLayout>,
+    /// The movie's output in the current session.
+    console: RefCell<Rc<RefCell<ConsoleBuffer>>>,
+    /// The user's answers to socket prompts, by `host:port`.
+    socket_answers: RefCell<HashMap<String, bool>>,
+    player: RefCell<Option<Arc<Mutex<Player>>>>,
+    executor: RefCell<Option<Arc<Executor>>>,
 }
 
+/// Forwards the movie's requests to the user.
 #[derive(Clone)]
-struct Navigator;
+struct Navigator {
+    controller: Arc<MainThreadBound<Weak<PlayerController>>>,
+    sandbox: Arc<FileSandbox>,
+}
 
 impl NavigatorInterface for Navigator {
-    fn navigate_to_website(&self, _url: Url, _ask: bool) {}
+    fn navigate_to_website(&self, url: Url, ask: bool) {
+        // `ExternalNavigatorBackend` has already applied the `OpenURLMode`,
+        // `ask` is set when it's `OpenURLMode::Confirm`.
+        if !ask {
+            open_url(&url);
+            return;
+        }
+        let Some(mtm) = MainThreadMarker::new() else {
+            tracing::error!(%url, "navigating to website off the main thread");
+            return;
+        };
+        if let Some(controller) = self.controller.get(mtm).load() {
+            controller.confirm_open_url(url);
+        }
+    }
 
     fn open_file(&self, path: &Path) -> io::Result<File> {
-        File::open(path)
+        self.sandbox.open(path)
     }
 
-    async fn confirm_socket(&self, _host: &str, _port: u16) -> bool {
-        true
+    async fn confirm_socket(&self, host: &str, port: u16) -> bool {
+        let address = format!("{host}:{port}");
+        let answer = {
+            let Some(mtm) = MainThreadMarker::new() else {
+                tracing::error!(address, "confirming socket off the main thread");
+                return false;
+            };
+            let Some(controller) = self.controller.get(mtm).load() else {
+                return false;
+            };
+            controller.confirm_socket(address)
+        };
+        // Dropped without an answer if the player is closed.
+        answer.await.unwrap_or(false)
     }
 }
 
@@ -106,6 +212,11 @@ declare_class!(
             self.view_did_load();
         }
 
+        #[method(viewDidLayoutSubviews)]
+        fn _view_did_layout_subviews(&self) {
+            self.view_did_layout_subviews();
+        }
+
         #[method(viewIsAppearing:)]
         fn _view_is_appearing(&self, animated: bool) {
             self.view_is_appearing(animated);
@@ -139,7 +250,7 @@ declare_class!(
         #[method(becomeFirstResponder)]
         fn becomeFirstResponder(&self) -> bool {
             tracing::info!("player controller becomeFirstResponder");
-            unsafe { self.view().becomeFirstResponder() };
+            unsafe { self.player_view().becomeFirstResponder() };
             true
         }
 
@@ -157,136 +268,1021 @@ declare_class!(
 );
 
 impl PlayerController {
-    pub fn new(mtm: MainThreadMarker, movie_path: String) -> Retained<Self> {
+    pub fn new(mtm: MainThreadMarker, launch: Launch) -> Retained<Self> {
         let this = mtm.alloc().set_ivars(Ivars {
-            movie_path: Some(movie_path),
-            player: OnceCell::new(),
-            executor: OnceCell::new(),
+            launch: OnceCell::from(launch),
+            ..Default::default()
         });
         let nil = ptr::null::<AnyObject>();
         unsafe { msg_send_id![super(this), initWithNibName: nil, bundle: nil] }
     }
 
+    /// Set what to launch, for controllers created by the storyboard.
+    ///
+    /// Must be called before the view is loaded.
+    pub fn configure(&self, launch: Launch) {
+        self.ivars()
+            .launch
+            .set(launch)
+            .unwrap_or_else(|_| panic!("only configure once"));
+    }
+
+    /// Set the library that transient movies may be imported into.
+    pub fn set_library(&self, library: Library) {
+        if self.ivars().library.set(library).is_err() {
+            tracing::warn!("library already set");
+        }
+    }
+
+    /// Set what to do once the player is closed, e.g. removing the file that
+    /// was played.
+    ///
+    /// If the movie is offered for import, this waits until the user has
+    /// answered, so that the file is still there to import.
+    pub fn set_close_handler(&self, handler: impl FnOnce() + 'static) {
+        *self.ivars().close_handler.borrow_mut() = Some(Box::new(handler));
+    }
+
+    /// Close without offering to import the movie, because another movie is
+    /// about to be shown in its place.
+    pub fn set_replaced(&self) {
+        self.ivars().replaced.set(true);
+    }
+
     fn load_view(&self) {
         tracing::info!("player loadView");
         let mtm = MainThreadMarker::from(self);
-        let view = PlayerView::initWithFrame(
-            mtm.alloc(),
-            CGRect::new(CGPoint::ZERO, CGSize::new(1.0, 1.0)),
-        );
+        let frame = CGRect::new(CGPoint::ZERO, CGSize::new(1.0, 1.0));
+        // The player view is placed inside the safe area, or covers the
+        // whole screen, in `viewDidLayoutSubviews`.
+        let view = UIView::initWithFrame(mtm.alloc(), frame);
+        unsafe { view.setBackgroundColor(Some(&UIColor::blackColor())) };
+        let player_view = PlayerView::initWithFrame(mtm.alloc(), frame);
+        player_view.set_uses_display_link(sync_rendering_to_display());
+        unsafe { view.addSubview(&player_view) };
+
+        // Loading progress and errors are shown on top of the player
+        unsafe {
+            let progress_view = UIProgressView::initWithProgressViewStyle(
+                mtm.alloc(),
+                UIProgressViewStyle::Default,
+            );
+            progress_view.setHidden(true);
+            view.addSubview(&progress_view);
+            self.ivars()
+                .progress_view
+                .set(progress_view)
+                .unwrap_or_else(|_| panic!("loadView once"));
+
+            let status_label = UILabel::initWithFrame(mtm.alloc(), CGRect::ZERO);
+            status_label.setNumberOfLines(0);
+            status_label.setTextAlignment(NSTextAlignment::Center);
+            status_label.setTextColor(Some(&UIColor::whiteColor()));
+            status_label.setHidden(true);
+            view.addSubview(&status_label);
+            self.ivars()
+                .status_label
+                .set(status_label)
+                .unwrap_or_else(|_| panic!("loadView once"));
+
+            let this = Weak::new(self);
+            let retry_button = error_button(ns_string!("Retry"), mtm, move || {
+                if let Some(this) = this.load() {
+                    this.restart();
+                }
+            });
+            view.addSubview(&retry_button);
+            self.ivars()
+                .retry_button
+                .set(retry_button)
+                .unwrap_or_else(|_| panic!("loadView once"));
+
+            let this = Weak::new(self);
+            let back_button = error_button(ns_string!("Back to Library"), mtm, move || {
+                if let Some(this) = this.load() {
+                    if let Some(navigation_controller) = this.navigationController() {
+                        navigation_controller.popViewControllerAnimated(true);
+                    }
+                }
+            });
+            view.addSubview(&back_button);
+            self.ivars()
+                .back_button
+                .set(back_button)
+                .unwrap_or_else(|_| panic!("loadView once"));
+        }
+
+        let this = Weak::new(self);
+        player_view.set_error_handler(move |message| {
+            if let Some(this) = this.load() {
+                this.show_error(&format!("The movie crashed:\n{message}"));
+            }
+        });
+        self.ivars()
+            .player_view
+            .set(player_view)
+            .unwrap_or_else(|_| panic!("loadView once"));
+
         self.setView(Some(&view));
     }
 
+    /// Also called when the safe area changes, e.g. when rotating the
+    /// device, or when the navigation bar is shown or hidden.
+    fn view_did_layout_subviews(&self) {
+        let view = (**self).view().expect("controller loads view");
+        let bounds = view.bounds();
+
+        // Touches are located relative to the player view, so they map to
+        // the stage regardless of where the view is placed. The player view
+        // updates the viewport dimensions when its frame changes.
+        let player_frame = match self.ivars().layout.get() {
+            ViewportLayout::FullBleed => bounds,
+            ViewportLayout::SafeArea => {
+                let insets = view.safeAreaInsets();
+                CGRect::new(
+                    CGPoint::new(bounds.origin.x + insets.left, bounds.origin.y + insets.top),
+                    CGSize::new(
+                        (bounds.size.width - insets.left - insets.right).max(0.0),
+                        (bounds.size.height - insets.top - insets.bottom).max(0.0),
+                    ),
+                )
+            }
+        };
+        self.player_view().setFrame(player_frame);
+
+        let margin = 20.0;
+        let width = (bounds.size.width - 2.0 * margin).max(0.0);
+        let middle = bounds.size.height / 2.0;
+        unsafe {
+            self.status_label().setFrame(CGRect::new(
+                CGPoint::new(margin, middle - 160.0),
+                CGSize::new(width, 150.0),
+            ));
+            self.progress_view().setFrame(CGRect::new(
+                CGPoint::new(margin, middle),
+                CGSize::new(width, 4.0),
+            ));
+            self.retry_button().setFrame(CGRect::new(
+                CGPoint::new(margin, middle),
+                CGSize::new(width, 44.0),
+            ));
+            self.back_button().setFrame(CGRect::new(
+                CGPoint::new(margin, middle + 44.0),
+                CGSize::new(width, 44.0),
+            ));
+        }
+    }
+
+    fn progress_view(&self) -> &UIProgressView {
+        self.ivars().progress_view.get().expect("view loaded")
+    }
+
+    fn status_label(&self) -> &UILabel {
+        self.ivars().status_label.get().expect("view loaded")
+    }
+
+    fn retry_button(&self) -> &UIButton {
+        self.ivars().retry_button.get().expect("view loaded")
+    }
+
+    fn back_button(&self) -> &UIButton {
+        self.ivars().back_button.get().expect("view loaded")
+    }
+
+    /// Stop the player, and show the error instead.
+    fn show_error(&self, message: &str) {
+        tracing::error!("showing error: {message}");
+        self.player_view().clear_player();
+        // Nothing is playing anymore, so let the screen lock again
+        set_idle_timer_disabled(MainThreadMarker::from(self), false);
+        unsafe {
+            self.progress_view().setHidden(true);
+            self.status_label()
+                .setText(Some(&NSString::from_str(message)));
+            self.status_label().setHidden(false);
+            self.retry_button().setHidden(false);
+            // There's nowhere to go back to when launched directly.
+            self.back_button()
+                .setHidden(self.navigationController().is_none());
+        }
+    }
+
+    /// Reload the movie from the beginning, also used to retry after errors.
+    fn restart(&self) {
+        tracing::info!("restarting");
+        unsafe {
+            self.status_label().setHidden(true);
+            self.retry_button().setHidden(true);
+            self.back_button().setHidden(true);
+        }
+        self.ivars().paused.set(false);
+        self.update_playback_controls();
+        self.start_player();
+        self.resume_playback();
+    }
+
+    /// Start playing, unless the user paused, or the scene isn't active.
+    fn resume_playback(&self) {
+        if self.ivars().paused.get() || self.ivars().suspended.get() {
+            return;
+        }
+        // Movies are often played without touching the screen
+        if self.player_view().start() {
+            set_idle_timer_disabled(MainThreadMarker::from(self), true);
+        }
+    }
+
+    fn stop_playback(&self) {
+        self.player_view().stop();
+        set_idle_timer_disabled(MainThreadMarker::from(self), false);
+    }
+
+    /// Stop playing while the scene isn't active, e.g. during a phone call.
+    pub fn suspend(&self) {
+        tracing::info!("suspending player");
+        self.ivars().suspended.set(true);
+        if self.isViewLoaded() {
+            self.stop_playback();
+        }
+    }
+
+    /// Continue playing once the scene is active again, if we were playing
+    /// before.
+    pub fn resume(&self) {
+        tracing::info!("resuming player");
+        self.ivars().suspended.set(false);
+        if self.isViewLoaded() {
+            self.resume_playback();
+        }
+    }
+
+    /// Save the movie's data, e.g. before the app may be terminated in the
+    /// background.
+    pub fn flush_shared_objects(&self) {
+        if self.isViewLoaded() {
+            self.player_view()
+                .with_player(|player| player.flush_shared_objects());
+        }
+    }
+
+    fn toggle_paused(&self) {
+        let paused = !self.ivars().paused.get();
+        tracing::info!(paused, "toggling pause");
+        self.ivars().paused.set(paused);
+        if paused {
+            self.stop_playback();
+        } else {
+            self.resume_playback();
+        }
+        self.update_playback_controls();
+    }
+
+    fn display_item(&self) -> &UIBarButtonItem {
+        self.ivars().display_item.get_or_init(|| {
+            let mtm = MainThreadMarker::from(self);
+            unsafe {
+                let image = UIImage::systemImageNamed(ns_string!("aspectratio"));
+                UIBarButtonItem::initWithImage_menu(mtm.alloc(), image.as_deref(), None)
+            }
+        })
+    }
+
+    fn display_options(&self) -> Option<DisplayOptions> {
+        self.player_view().with_player(|player| {
+            let (scale, quality) = player.mutate_with_update_context(|context| {
+                (context.stage.scale_mode(), context.stage.quality())
+            });
+            DisplayOptions {
+                scale,
+                letterbox: player.letterbox(),
+                quality,
+            }
+        })
+    }
+
+    fn set_display_options(&self, f: impl FnOnce(&mut DisplayOptions)) {
+        let Some(mut options) = self.display_options() else {
+            return;
+        };
+        f(&mut options);
+        tracing::info!(?options, "changing display options");
+        self.ivars().display_options.set(Some(options));
+        self.player_view().with_player(|player| {
+            player.set_letterbox(options.letterbox);
+            player.mutate_with_update_context(|context| {
+                context.stage.set_scale_mode(context, options.scale);
+                context.stage.set_quality(context, options.quality);
+            });
+        });
+        self.update_display_menu();
+    }
+
+    fn set_layout(&self, layout: ViewportLayout) {
+        tracing::info!(?layout, "changing layout");
+        self.ivars().layout.set(layout);
+        if let Some(view) = (**self).view() {
+            view.setNeedsLayout();
+        }
+        self.update_display_menu();
+    }
+
+    /// Store the current display options in the library item's settings.
+    fn save_display_options(&self) {
+        let (Some(launch), Some(options)) = (self.ivars().launch.get(), self.display_options())
+        else {
+            return;
+        };
+        let layout = self.ivars().layout.get();
+        let res = launch.update_item(|item| {
+            let player = &mut item.info.player;
+            player.scale = Some(options.scale);
+            player.letterbox = Some(options.letterbox);
+            player.quality = Some(options.quality);
+            item.settings.layout = Some(layout);
+        });
+        match res {
+            Ok(()) => tracing::info!(?options, "saved display options"),
+            Err(err) => tracing::error!("failed saving display options: {err}"),
+        }
+    }
+
+    /// Rebuild the display menu to show the current options.
+    fn update_display_menu(&self) {
+        let mtm = MainThreadMarker::from(self);
+        let current = self.display_options();
+
+        let scale_actions: Vec<_> = SCALE_MODES
+            .iter()
+            .map(|&(title, scale)| {
+                let action = self.menu_action(title, move |this| {
+                    this.set_display_options(|options| options.scale = scale);
+                });
+                if current.is_some_and(|current| current.scale == scale) {
+                    unsafe { action.setState(UIMenuElementState::On) };
+                }
+                Retained::into_super(action)
+            })
+            .collect();
+
+        let quality_actions: Vec<_> = QUALITIES
+            .iter()
+            .map(|&(title, quality)| {
+                let action = self.menu_action(title, move |this| {
+                    this.set_display_options(|options| options.quality = quality);
+                });
+                if current.is_some_and(|current| current.quality == quality) {
+                    unsafe { action.setState(UIMenuElementState::On) };
+                }
+                Retained::into_super(action)
+            })
+            .collect();
+
+        let letterboxed = current.is_some_and(|current| current.letterbox != Letterbox::Off);
+        let letterbox_action = self.menu_action("Letterbox", move |this| {
+            this.set_display_options(|options| {
+                options.letterbox = if letterboxed {
+                    Letterbox::Off
+                } else {
+                    Letterbox::On
+                };
+            });
+        });
+        if letterboxed {
+            unsafe { letterbox_action.setState(UIMenuElementState::On) };
+        }
+
+        let full_bleed = self.ivars().layout.get() == ViewportLayout::FullBleed;
+        let full_bleed_action = self.menu_action("Extend Behind Notch", move |this| {
+            this.set_layout(if full_bleed {
+                ViewportLayout::SafeArea
+            } else {
+                ViewportLayout::FullBleed
+            });
+        });
+        if full_bleed {
+            unsafe { full_bleed_action.setState(UIMenuElementState::On) };
+        }
+
+        let save_action = self.menu_action("Save for This Movie", |this| {
+            this.save_display_options();
+        });
+        let can_save = current.is_some()
+            && self
+                .ivars()
+                .launch
+                .get()
+                .is_some_and(|launch| !launch.is_transient());
+        if !can_save {
+            unsafe { save_action.setAttributes(UIMenuElementAttributes::Disabled) };
+        }
+
+        unsafe {
+            let scale_menu = UIMenu::menuWithTitle_image_identifier_options_children(
+                ns_string!("Scale Mode"),
+                None,
+                None,
+                UIMenuOptions::SingleSelection,
+                &NSArray::from_vec(scale_actions),
+                mtm,
+            );
+            let quality_menu = UIMenu::menuWithTitle_image_identifier_options_children(
+                ns_string!("Quality"),
+                None,
+                None,
+                UIMenuOptions::SingleSelection,
+                &NSArray::from_vec(quality_actions),
+                mtm,
+            );
+            let save_section = UIMenu::menuWithTitle_image_identifier_options_children(
+                ns_string!(""),
+                None,
+                None,
+                UIMenuOptions::DisplayInline,
+                &NSArray::from_slice(&[&**save_action]),
+                mtm,
+            );
+            let menu = UIMenu::menuWithTitle_children(
+                ns_string!(""),
+                &NSArray::from_slice(&[
+                    &**scale_menu,
+                    &**quality_menu,
+                    &**letterbox_action,
+                    &**full_bleed_action,
+                    &**save_section,
+                ]),
+                mtm,
+            );
+            self.display_item().setMenu(Some(&menu));
+        }
+    }
+
+    fn menu_action(&self, title: &str, f: impl Fn(&Self) + 'static) -> Retained<UIAction> {
+        let mtm = MainThreadMarker::from(self);
+        let this = Weak::new(self);
+        let action = action(mtm, move || {
+            if let Some(this) = this.load() {
+                f(&this);
+            }
+        });
+        unsafe { action.setTitle(&NSString::from_str(title)) };
+        action
+    }
+
+    fn step_frame(&self) {
+        tracing::debug!("stepping frame");
+        self.player_view().step_frame();
+    }
+
+    /// Show the playback controls in the navigation bar, matching whether
+    /// we're paused.
+    fn update_playback_controls(&self) {
+        let mtm = MainThreadMarker::from(self);
+        let paused = self.ivars().paused.get();
+
+        let this = Weak::new(self);
+        let toggle = bar_button_item(
+            if paused {
+                UIBarButtonSystemItem::Play
+            } else {
+                UIBarButtonSystemItem::Pause
+            },
+            mtm,
+            move || {
+                if let Some(this) = this.load() {
+                    this.toggle_paused();
+                }
+            },
+        );
+
+        // Stepping only makes sense while paused
+        let this = Weak::new(self);
+        let step = bar_button_item(UIBarButtonSystemItem::FastForward, mtm, move || {
+            if let Some(this) = this.load() {
+                this.step_frame();
+            }
+        });
+        unsafe { step.setEnabled(paused) };
+
+        let this = Weak::new(self);
+        let restart = bar_button_item(UIBarButtonSystemItem::Refresh, mtm, move || {
+            if let Some(this) = this.load() {
+                this.restart();
+            }
+        });
+
+        let this = Weak::new(self);
+        let console = image_bar_button_item(ns_string!("terminal"), mtm, move || {
+            if let Some(this) = this.load() {
+                this.show_console();
+            }
+        });
+
+        // The first item is shown rightmost
+        let items = NSArray::from_vec(vec![
+            toggle,
+            step,
+            restart,
+            self.display_item().retain(),
+            console,
+        ]);
+        unsafe { self.navigationItem().setRightBarButtonItems(Some(&items)) };
+    }
+
+    /// Show the output of the current session.
+    fn show_console(&self) {
+        let mtm = MainThreadMarker::from(self);
+        let name = self
+            .ivars()
+            .launch
+            .get()
+            .map_or_else(|| "Ruffle".into(), |launch| launch.info.name.clone());
+        let console = ConsoleController::new(mtm, name, self.ivars().console.borrow().clone());
+        unsafe {
+            let navigation_controller =
+                UINavigationController::initWithRootViewController(mtm.alloc(), &console);
+            self.presentViewController_animated_completion(&navigation_controller, true, None);
+        }
+    }
+
+    /// Fetch the root movie through the navigator, so that both local files
+    /// and remote URLs work.
+    fn start_loading(&self, navigator: &mut impl NavigatorBackend, info: &BundleInformation) {
+        tracing::info!(url = %info.url, "loading root movie");
+        unsafe {
+            self.status_label()
+                .setText(Some(&NSString::from_str(&format!(
+                    "Loading {}…",
+                    info.name
+                ))));
+            self.status_label().setHidden(false);
+            self.progress_view().setProgress_animated(0.0, false);
+            self.progress_view().setHidden(false);
+        }
+
+        let fetch = navigator.fetch(Request::get(info.url.to_string()));
+        let parameters = info.player.parameters.clone();
+        let session = self.ivars().session.get();
+        let this = Weak::new(self);
+        let progress_this = this.clone();
+        navigator.spawn_future(Box::pin(async move {
+            // The executor is polled on the main thread, see `MainThreadPoller`.
+            let res = load_root_movie(fetch, parameters, move |loaded, total| {
+                if let Some(this) = progress_this
+                    .load()
+                    .filter(|t| t.ivars().session.get() == session)
+                {
+                    this.loading_progress(loaded, total);
+                }
+            })
+            .await;
+            if let Some(this) = this.load().filter(|t| t.ivars().session.get() == session) {
+                this.root_movie_loaded(res);
+            }
+            Ok(())
+        }));
+    }
+
+    fn loading_progress(&self, loaded: u64, total: Option<u64>) {
+        unsafe {
+            if let Some(total) = total.filter(|total| *total > 0) {
+                self.progress_view()
+                    .setProgress_animated(loaded as f32 / total as f32, true);
+            }
+        }
+        tracing::trace!(loaded, total, "root movie progress");
+    }
+
+    fn root_movie_loaded(&self, res: Result<SwfMovie, LaunchError>) {
+        unsafe { self.progress_view().setHidden(true) };
+        match res {
+            Ok(movie) => {
+                tracing::info!("loaded root movie");
+                unsafe { self.status_label().setHidden(true) };
+                self.player_view()
+                    .with_player(|player| player.set_root_movie(movie));
+                // The movie may have its own defaults
+                self.update_display_menu();
+            }
+            Err(err) => self.show_error(&format!("Could not load movie:\n{err}")),
+        }
+    }
+
     fn view_did_load(&self) {
         tracing::info!("player viewDidLoad");
+        if let Some(layout) = self.ivars().launch.get().and_then(|l| l.settings.layout) {
+            self.ivars().layout.set(layout);
+        }
+        self.update_playback_controls();
+        self.start_player();
+    }
 
-        // TODO: Specify safe area somehow
-        let view = self.view();
-        let renderer = view.create_renderer();
+    /// Create the player, or show an error if that fails.
+    fn start_player(&self) {
+        self.ivars()
+            .session
+            .set(self.ivars().session.get().wrapping_add(1));
+        // Save the old movie's data before it is replaced, e.g. on restart
+        self.flush_shared_objects();
+        self.ivars().player.borrow_mut().take();
+        self.ivars().executor.borrow_mut().take();
 
-        let sender = EventSender(Rc::new(OnceCell::new()));
-        let (executor, future_spawner) = AsyncExecutor::new(sender.clone());
-        sender
-            .0
-            .set(executor.clone())
-            .unwrap_or_else(|_| panic!("init once"));
+        if let Err(err) = self.try_start_player() {
+            self.show_error(&format!("Could not start the player:\n{err}"));
+        }
+        self.update_display_menu();
+    }
 
-        let movie_url = Url::parse("file://movie.swf").unwrap();
-        let navigator = ExternalNavigatorBackend::new(
-            movie_url.clone(),
-            None,
-            None,
-            future_spawner,
-            None,
-            true,
-            ruffle_core::backend::navigator::OpenURLMode::Allow,
-            Default::default(),
-            ruffle_core::backend::navigator::SocketMode::Allow,
-            Rc::new(PlayingContent::DirectFile(movie_url)),
-            Navigator,
-        );
+    fn try_start_player(&self) -> Result<(), LaunchError> {
+        let mtm = MainThreadMarker::from(self);
+        let view = self.player_view();
+        let renderer = view.create_renderer()?;
 
-        let mut builder = PlayerBuilder::new()
-            .with_renderer(renderer)
-            .with_navigator(navigator);
+        let poller = MainThreadPoller::new();
+        let (executor, future_spawner) = AsyncExecutor::new(poller.clone());
+        poller.set_executor(&executor, mtm);
 
-        // Temporary until we figure out actual loading
-        let movie = if let Some(path) = self.ivars().movie_path.as_deref() {
-            SwfMovie::from_path(path, None).expect("failed loading movie")
+        // The logo is loaded from the asset catalog, everything else is
+        // loaded asynchronously through the navigator below.
+        let (info, content, logo) = if let Some(launch) = self.ivars().launch.get() {
+            let content = launch.content()?;
+            (launch.info.clone(), content, None)
         } else {
             let asset =
                 unsafe { NSDataAsset::initWithName(NSDataAsset::alloc(), ns_string!("logo-anim")) }
                     .expect("asset store should contain logo-anim");
             let data = unsafe { asset.data() };
-            SwfMovie::from_data(data.bytes(), "file://logo-anim.swf".into(), None)
-                .expect("loading movie")
+            let info = BundleInformation {
+                name: "Ruffle".into(),
+                url: Url::parse("file:///logo-anim.swf").unwrap(),
+                player: PlayerOptions::default(),
+            };
+            let movie = SwfMovie::from_data(data.bytes(), info.url.to_string(), None)?;
+            let content = PlayingContent::DirectFile(info.url.clone());
+            (info, content, Some(movie))
         };
-        builder = builder.with_movie(movie);
+        let options = &info.player;
+        let settings = self
+            .ivars()
+            .launch
+            .get()
+            .map(|launch| launch.settings.clone())
+            .unwrap_or_default();
+        let mut socket_answers = self.ivars().socket_answers.borrow_mut();
+        socket_answers.clear();
+        for address in &settings.socket_allowed {
+            socket_answers.insert(address.clone(), true);
+        }
+        for address in &settings.socket_denied {
+            socket_answers.insert(address.clone(), false);
+        }
+        drop(socket_answers);
+
+        let mut navigator = ExternalNavigatorBackend::new(
+            options.base.clone().unwrap_or_else(|| info.url.clone()),
+            options.referer.clone(),
+            options.cookie.clone(),
+            future_spawner,
+            None,
+            options.upgrade_to_https.unwrap_or_default(),
+            settings.open_url_mode.unwrap_or(OpenURLMode::Confirm),
+            settings.socket_allowed.iter().cloned().collect(),
+            settings.socket_mode.unwrap_or(SocketMode::Ask),
+            Rc::new(content),
+            Navigator {
+                controller: Arc::new(MainThreadBound::new(Weak::new(self), mtm)),
+                sandbox: Arc::new(
+                    self.ivars()
+                        .launch
+                        .get()
+                        .map(Launch::file_sandbox)
+                        .unwrap_or_default(),
+                ),
+            },
+        );
+
+        if logo.is_none() {
+            self.start_loading(&mut navigator, &info);
+        }
+
+        // Each session starts with an empty console
+        let console = Rc::new(RefCell::new(ConsoleBuffer::default()));
+        *self.ivars().console.borrow_mut() = console.clone();
+
+        let mut builder = PlayerBuilder::new()
+            .with_renderer(renderer)
+            .with_navigator(navigator)
+            .with_log(ConsoleLogBackend::new(console));
+        if let Some(movie) = logo {
+            builder = builder.with_movie(movie);
+        }
+        builder = apply_player_options(builder, options);
+        if let Some(display) = self.ivars().display_options.get() {
+            builder = builder
+                .with_scale_mode(display.scale, options.force_scale.unwrap_or_default())
+                .with_letterbox(display.letterbox)
+                .with_quality(display.quality);
+        }
+
+        if let Some(app_data_dir) = self.ivars().launch.get().and_then(|l| l.app_data_dir()) {
+            builder = builder.with_storage(Box::new(DiskStorageBackend::new(app_data_dir)));
+        }
 
         match CpalAudioBackend::new(None) {
             Ok(audio) => builder = builder.with_audio(audio),
             Err(e) => tracing::error!("Unable to create audio device: {e}"),
         }
 
-        let player = builder.build();
-
-        let mut player_lock = player.lock().unwrap();
-        // player_lock.fetch_root_movie(
-        //     self.ivars().movie_url.clone(),
-        //     vec![],
-        //     Box::new(|metadata| {
-        //         eprintln!("got movie: {:?}", metadata);
-        //     }),
-        // );
-        player_lock.set_letterbox(Letterbox::On);
-        drop(player_lock);
+        let player = panic::catch_unwind(AssertUnwindSafe(|| {
+            let player = builder.build();
+            apply_player_options_after_build(&mut player.lock().unwrap(), options);
+            player
+        }))
+        .map_err(|payload| LaunchError::Panicked(panic_message(&*payload)))?;
 
         view.set_player(player.clone());
-        self.ivars()
-            .player
-            .set(player)
-            .unwrap_or_else(|_| panic!("viewDidLoad once"));
-        self.ivars()
-            .executor
-            .set(executor)
-            .unwrap_or_else(|_| panic!("viewDidLoad once"));
+        *self.ivars().player.borrow_mut() = Some(player);
+        *self.ivars().executor.borrow_mut() = Some(executor);
+        Ok(())
     }
 
     fn view_is_appearing(&self, _animated: bool) {
         tracing::info!("player viewIsAppearing:");
 
-        self.view().start();
+        self.resume_playback();
     }
 
     fn view_will_disappear(&self, _animated: bool) {
         tracing::info!("player viewWillDisappear:");
 
-        self.view().stop();
+        self.stop_playback();
+
+        // The navigation controller is gone once the view has disappeared,
+        // so we have to check and fetch it here.
+        if unsafe { self.isMovingFromParentViewController() } {
+            let close_handler = self.ivars().close_handler.take();
+            let launch = self
+                .ivars()
+                .launch
+                .get()
+                .filter(|l| l.is_transient() && !self.ivars().replaced.get());
+            let navigation_controller = unsafe { self.navigationController() };
+            if let (Some(launch), Some(library), Some(navigation_controller)) =
+                (launch, self.ivars().library.get(), navigation_controller)
+            {
+                offer_import(&navigation_controller, library, launch, close_handler);
+            } else if let Some(handler) = close_handler {
+                handler();
+            }
+        }
     }
 
     fn view_did_disappear(&self, _animated: bool) {
         tracing::info!("player viewDidDisappear:");
 
-        self.player_lock().flush_shared_objects();
+        self.player_view()
+            .with_player(|player| player.flush_shared_objects());
     }
 
-    fn view(&self) -> Retained<PlayerView> {
-        let view = (**self).view().expect("controller loads view");
-        assert!(
-            view.isKindOfClass(PlayerView::class()),
-            "must have correct view type"
-        );
-        // SAFETY: Just checked that the view is of type `PlayerView`
-        unsafe { Retained::cast(view) }
+    /// Ask the user before letting the movie open a website.
+    fn confirm_open_url(&self, url: Url) {
+        let mtm = MainThreadMarker::from(self);
+        // Movies may navigate repeatedly, don't stack up prompts.
+        if unsafe { self.presentedViewController() }.is_some() {
+            tracing::warn!(%url, "ignoring navigation while another prompt is shown");
+            return;
+        }
+
+        let message = NSString::from_str(url.as_str());
+        let open_block = RcBlock::new(move |_: NonNull<UIAlertAction>| open_url(&url));
+        let open_ptr: *const Block<_> = &*open_block;
+
+        unsafe {
+            let alert = UIAlertController::alertControllerWithTitle_message_preferredStyle(
+                Some(ns_string!("Open website?")),
+                Some(&message),
+                UIAlertControllerStyle::Alert,
+                mtm,
+            );
+            alert.addAction(&UIAlertAction::actionWithTitle_style_handler(
+                Some(ns_string!("Cancel")),
+                UIAlertActionStyle::Cancel,
+                ptr::null_mut(),
+                mtm,
+            ));
+            alert.addAction(&UIAlertAction::actionWithTitle_style_handler(
+                Some(ns_string!("Open")),
+                UIAlertActionStyle::Default,
+                open_ptr.cast_mut(),
+                mtm,
+            ));
+            self.presentViewController_animated_completion(&alert, true, None);
+        }
     }
 
-    #[track_caller]
-    fn player_lock(&self) -> MutexGuard<'_, Player> {
-        self.ivars()
-            .player
+    /// Ask the user whether the movie may connect to `address`, and
+    /// remember the answer.
+    fn confirm_socket(&self, address: String) -> oneshot::Receiver<bool> {
+        let mtm = MainThreadMarker::from(self);
+        let (sender, receiver) = oneshot::channel();
+
+        if let Some(&allowed) = self.ivars().socket_answers.borrow().get(&address) {
+            let _ = sender.send(allowed);
+            return receiver;
+        }
+        if unsafe { self.presentedViewController() }.is_some() {
+            tracing::warn!(address, "denying socket while another prompt is shown");
+            let _ = sender.send(false);
+            return receiver;
+        }
+
+        let sender = Rc::new(Cell::new(Some(sender)));
+        let answer_block = |allowed: bool| {
+            let this = Weak::new(self);
+            let sender = sender.clone();
+            let address = address.clone();
+            RcBlock::new(move |_: NonNull<UIAlertAction>| {
+                if let Some(this) = this.load() {
+                    this.remember_socket(&address, allowed);
+                }
+                if let Some(sender) = sender.take() {
+                    let _ = sender.send(allowed);
+                }
+            })
+        };
+        let deny_block = answer_block(false);
+        let deny_ptr: *const Block<_> = &*deny_block;
+        let allow_block = answer_block(true);
+        let allow_ptr: *const Block<_> = &*allow_block;
+
+        // Transient movies only remember the answer until they are closed
+        let remembered = self
+            .ivars()
+            .launch
             .get()
-            .expect("player initialized")
-            .lock()
-            .expect("player lock")
+            .is_some_and(|launch| !launch.is_transient());
+        let message = if remembered {
+            format!(
+                "The movie wants to connect to {address}. Your answer is remembered for this movie."
+            )
+        } else {
+            format!("The movie wants to connect to {address}.")
+        };
+        let message = NSString::from_str(&message);
+        unsafe {
+            let alert = UIAlertController::alertControllerWithTitle_message_preferredStyle(
+                Some(ns_string!("Allow network connection?")),
+                Some(&message),
+                UIAlertControllerStyle::Alert,
+                mtm,
+            );
+            alert.addAction(&UIAlertAction::actionWithTitle_style_handler(
+                Some(ns_string!("Deny")),
+                UIAlertActionStyle::Cancel,
+                deny_ptr.cast_mut(),
+                mtm,
+            ));
+            alert.addAction(&UIAlertAction::actionWithTitle_style_handler(
+                Some(ns_string!("Allow")),
+                UIAlertActionStyle::Default,
+                allow_ptr.cast_mut(),
+                mtm,
+            ));
+            self.presentViewController_animated_completion(&alert, true, None);
+        }
+        receiver
+    }
+
+    fn remember_socket(&self, address: &str, allowed: bool) {
+        tracing::info!(address, allowed, "remembering socket answer");
+        self.ivars()
+            .socket_answers
+            .borrow_mut()
+            .insert(address.to_string(), allowed);
+
+        let Some(launch) = self.ivars().launch.get() else {
+            return;
+        };
+        let res = launch.update_item(|item| {
+            let settings = &mut item.settings;
+            settings.socket_allowed.retain(|a| a != address);
+            settings.socket_denied.retain(|a| a != address);
+            if allowed {
+                settings.socket_allowed.push(address.to_string());
+            } else {
+                settings.socket_denied.push(address.to_string());
+            }
+        });
+        if let Err(err) = res {
+            tracing::error!(address, "failed storing socket answer: {err}");
+        }
+    }
+
+    fn player_view(&self) -> &PlayerView {
+        self.loadViewIfNeeded();
+        self.ivars().player_view.get().expect("view loaded")
+    }
+}
+
+/// Create a button for the error screen, hidden until an error occurs.
+fn error_button(
+    title: &NSString,
+    mtm: MainThreadMarker,
+    f: impl Fn() + 'static,
+) -> Retained<UIButton> {
+    unsafe {
+        let button = UIButton::buttonWithType(UIButtonType::System, mtm);
+        button.setTitle_forState(Some(title), UIControlState::Normal);
+        button.setHidden(true);
+        button.addAction_forControlEvents(&action(mtm, f), UIControlEvents::TouchUpInside);
+        button
+    }
+}
+
+fn set_idle_timer_disabled(mtm: MainThreadMarker, disabled: bool) {
+    unsafe { UIApplication::sharedApplication(mtm).setIdleTimerDisabled(disabled) };
+}
+
+fn open_url(url: &Url) {
+    tracing::info!(%url, "opening website");
+    if let Err(err) = webbrowser::open(url.as_str()) {
+        tracing::error!(%url, "failed opening website: {err}");
+    }
+}
+
+/// Ask the user whether to keep a transient movie in the library, and call
+/// `on_answer` afterwards.
+///
+/// Settings and save data from the session itself are not kept.
+fn offer_import(
+    navigation_controller: &UINavigationController,
+    library: &Library,
+    launch: &Launch,
+    on_answer: Option<Box<dyn FnOnce()>>,
+) {
+    let mtm = MainThreadMarker::from(navigation_controller);
+    let source = launch.source.clone();
+    let info = launch.info.clone();
+    let library = library.clone();
+    let title = NSString::from_str(&format!(
+        "Import \"{}\" into the library?",
+        launch.info.name
+    ));
+
+    let on_answer = Rc::new(Cell::new(on_answer));
+    let discard_block = {
+        let on_answer = on_answer.clone();
+        RcBlock::new(move |_: NonNull<UIAlertAction>| {
+            if let Some(on_answer) = on_answer.take() {
+                on_answer();
+            }
+        })
+    };
+    let discard_ptr: *const Block<_> = &*discard_block;
+
+    let navigation_controller = navigation_controller.retain();
+    let import_block = RcBlock::new(move |_: NonNull<UIAlertAction>| {
+        let res = match &source {
+            ContentSource::TransientSwf(path) => import_swf(&library, path),
+            ContentSource::TransientBundle(path) => import_ruf(&library, path),
+            // Only the link to the movie is kept, like items added by URL
+            ContentSource::Remote => library.insert(info.clone()).map_err(ImportError::from),
+            ContentSource::Library { .. } => {
+                unreachable!("only transient launches are offered for import")
+            }
+        };
+        match res {
+            Ok(item) => tracing::info!(id = %item.id, "imported transient movie"),
+            Err(err) => tracing::error!(?source, "failed importing transient movie: {err}"),
+        }
+        // Show the new item
+        reload_library(&navigation_controller);
+        if let Some(on_answer) = on_answer.take() {
+            on_answer();
+        }
+    });
+    let import_ptr: *const Block<_> = &*import_block;
+
+    unsafe {
+        let alert = UIAlertController::alertControllerWithTitle_message_preferredStyle(
+            Some(&title),
+            Some(ns_string!(
+                "Settings and saved data from this session will be discarded."
+            )),
+            UIAlertControllerStyle::Alert,
+            mtm,
+        );
+        alert.addAction(&UIAlertAction::actionWithTitle_style_handler(
+            Some(ns_string!("Discard")),
+            UIAlertActionStyle::Cancel,
+            discard_ptr.cast_mut(),
+            mtm,
+        ));
+        alert.addAction(&UIAlertAction::actionWithTitle_style_handler(
+            Some(ns_string!("Import")),
+            UIAlertActionStyle::Default,
+            import_ptr.cast_mut(),
+            mtm,
+        ));
+        navigation_controller.presentViewController_animated_completion(&alert, true, None);
     }
 }
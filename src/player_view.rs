@@ -1,6 +1,6 @@
-use std::cell::{Cell, OnceCell};
-use std::sync::{Arc, Mutex, MutexGuard};
-use std::time::Instant;
+use std::cell::{Cell, OnceCell, RefCell};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Mutex};
 
 use objc2::rc::{Allocated, Retained};
 use objc2::runtime::AnyClass;
@@ -9,21 +9,31 @@ use objc2_foundation::{
     CGRect, MainThreadMarker, NSCoder, NSDate, NSObjectProtocol, NSRunLoop, NSRunLoopCommonModes,
     NSSet, NSTimer,
 };
-use objc2_quartz_core::{CALayer, CALayerDelegate, CAMetalLayer};
+use objc2_quartz_core::{CADisplayLink, CALayer, CALayerDelegate, CAMetalLayer};
 use objc2_ui_kit::{
     UIEvent, UIKeyboardHIDUsage, UIPress, UIPressPhase, UIPressesEvent, UITouch, UITouchPhase,
     UIView, UIViewContentMode,
 };
 use ruffle_core::events::{KeyCode, MouseButton};
 use ruffle_core::{Player, PlayerEvent, ViewportDimensions};
+use ruffle_render::error::Error as RenderError;
 use ruffle_render_wgpu::backend::WgpuRenderBackend;
 use ruffle_render_wgpu::target::SwapChainTarget;
 
+use crate::launch::panic_message;
+use crate::scheduler::FrameScheduler;
+
 #[derive(Default)]
 pub struct Ivars {
-    player: OnceCell<Arc<Mutex<Player>>>,
+    player: RefCell<Option<Arc<Mutex<Player>>>>,
+    error_handler: RefCell<Option<Box<dyn Fn(String)>>>,
     timer: OnceCell<Retained<NSTimer>>,
-    last_frame_time: Cell<Option<Instant>>,
+    scheduler: RefCell<FrameScheduler>,
+    /// Whether to render in sync with the display, instead of right after
+    /// ticking.
+    use_display_link: Cell<bool>,
+    /// Only exists while playing, since it retains the view.
+    display_link: RefCell<Option<Retained<CADisplayLink>>>,
 }
 
 declare_class!(
@@ -68,6 +78,11 @@ declare_class!(
         fn _timer_fire(&self, _timer: &NSTimer) {
             self.timer_fire();
         }
+
+        #[method(displayLinkFire:)]
+        fn _display_link_fire(&self, _display_link: &CADisplayLink) {
+            self.display_link_fire();
+        }
     }
 
     // UIResponder
@@ -251,8 +266,9 @@ impl PlayerView {
         // Create repeating timer that won't fire until we properly start it
         // (because of the high interval).
         //
-        // TODO: Consider running two timers, one to maintain the frame rate,
-        // and one to update Flash timers.
+        // The timer only ticks the player, which updates both frames and
+        // Flash timers. Rendering is requested separately, see
+        // `request_render`.
         let timer = unsafe {
             NSTimer::timerWithTimeInterval_target_selector_userInfo_repeats(
                 f64::MAX,
@@ -269,46 +285,81 @@ impl PlayerView {
     }
 
     pub fn set_player(&self, player: Arc<Mutex<Player>>) {
-        // TODO: Use `player.start_time` here to ensure that our deltas are
-        // correct.
-        self.ivars().last_frame_time.set(Some(Instant::now()));
-        self.ivars()
-            .player
-            .set(player)
-            .unwrap_or_else(|_| panic!("only init player once"));
+        // The player was just built, so its clock starts now. Don't pass it
+        // the time since the previous player was last ticked.
+        self.ivars().scheduler.borrow_mut().reset();
+        *self.ivars().player.borrow_mut() = Some(player);
     }
 
-    #[track_caller]
-    pub fn player_lock(&self) -> MutexGuard<'_, Player> {
-        self.ivars()
-            .player
-            .get()
-            .expect("player initialized")
-            .lock()
-            .expect("player lock")
+    /// Stop and remove the player, e.g. after it crashed.
+    pub fn clear_player(&self) {
+        self.stop_scheduling();
+        self.ivars().player.borrow_mut().take();
+    }
+
+    /// Render from a display link while playing, instead of right after
+    /// ticking.
+    ///
+    /// This avoids rendering more often than the display refreshes, and
+    /// renders in sync with it, at the cost of a frame of latency.
+    pub fn set_uses_display_link(&self, use_display_link: bool) {
+        self.ivars().use_display_link.set(use_display_link);
+    }
+
+    /// Set the function that is called with the panic message if the player
+    /// panics.
+    pub fn set_error_handler(&self, handler: impl Fn(String) + 'static) {
+        *self.ivars().error_handler.borrow_mut() = Some(Box::new(handler));
+    }
+
+    /// Run `f` with the player locked.
+    ///
+    /// Returns `None` if there is no player. Panics inside the player are
+    /// caught and reported to the error handler, and the player is removed
+    /// afterwards, since it may be in an inconsistent state.
+    pub fn with_player<R>(&self, f: impl FnOnce(&mut Player) -> R) -> Option<R> {
+        let player = self.ivars().player.borrow().clone()?;
+        let res = panic::catch_unwind(AssertUnwindSafe(|| {
+            // Cannot be poisoned, we remove the player if it panics.
+            let mut player_lock = player.lock().expect("player lock");
+            f(&mut player_lock)
+        }));
+        match res {
+            Ok(res) => Some(res),
+            Err(payload) => {
+                let message = panic_message(&*payload);
+                tracing::error!("player panicked: {message}");
+                self.clear_player();
+                if let Some(handler) = &*self.ivars().error_handler.borrow() {
+                    handler(message);
+                }
+                None
+            }
+        }
     }
 
     fn resize(&self) {
         tracing::info!("resizing to {:?}", self.frame().size);
         let new_dimensions = self.viewport_dimensions();
 
-        let mut player_lock = self.player_lock();
-        // Avoid unnecessary resizes
-        // FIXME: Expose `PartialEq` on `ViewportDimensions`.
-        let old_dimensions = player_lock.viewport_dimensions();
-        if new_dimensions.height != old_dimensions.height
-            || new_dimensions.width != old_dimensions.width
-            || new_dimensions.scale_factor != old_dimensions.scale_factor
-        {
-            player_lock.set_viewport_dimensions(new_dimensions);
-        }
+        self.with_player(|player| {
+            // Avoid unnecessary resizes
+            // FIXME: Expose `PartialEq` on `ViewportDimensions`.
+            let old_dimensions = player.viewport_dimensions();
+            if new_dimensions.height != old_dimensions.height
+                || new_dimensions.width != old_dimensions.width
+                || new_dimensions.scale_factor != old_dimensions.scale_factor
+            {
+                player.set_viewport_dimensions(new_dimensions);
+            }
+        });
     }
 
     fn draw_rect(&self) {
         tracing::trace!("drawing");
         // Render if the system asks for it because of a resize,
         // or if we asked for it with `setNeedsDisplay`.
-        self.player_lock().render();
+        self.with_player(|player| player.render());
     }
 
     pub fn viewport_dimensions(&self) -> ViewportDimensions {
@@ -321,7 +372,7 @@ impl PlayerView {
         }
     }
 
-    pub fn create_renderer(&self) -> WgpuRenderBackend<SwapChainTarget> {
+    pub fn create_renderer(&self) -> Result<WgpuRenderBackend<SwapChainTarget>, RenderError> {
         let layer = self.layer();
         let dimensions = self.viewport_dimensions();
         let layer_ptr = Retained::as_ptr(&layer).cast_mut().cast();
@@ -333,7 +384,6 @@ impl PlayerView {
                 wgpu::PowerPreference::HighPerformance,
                 None,
             )
-            .expect("creating renderer")
         }
     }
 
@@ -342,49 +392,112 @@ impl PlayerView {
         self.ivars().timer.get().expect("timer initialized")
     }
 
-    pub fn start(&self) {
-        self.player_lock().set_is_playing(true);
+    /// Start playing, returns whether there was a player to start.
+    pub fn start(&self) -> bool {
+        if self
+            .with_player(|player| player.set_is_playing(true))
+            .is_none()
+        {
+            return false;
+        }
+        self.ivars().scheduler.borrow_mut().start();
+
+        if self.ivars().use_display_link.get() && self.ivars().display_link.borrow().is_none() {
+            let display_link = unsafe {
+                CADisplayLink::displayLinkWithTarget_selector(self, sel!(displayLinkFire:))
+            };
+            unsafe {
+                display_link.addToRunLoop_forMode(&NSRunLoop::mainRunLoop(), NSRunLoopCommonModes)
+            };
+            *self.ivars().display_link.borrow_mut() = Some(display_link);
+        }
+
         unsafe { self.timer().fire() };
+        true
     }
 
     pub fn stop(&self) {
-        self.player_lock().set_is_playing(false);
+        self.with_player(|player| player.set_is_playing(false));
+        self.stop_scheduling();
+    }
+
+    fn stop_scheduling(&self) {
+        self.ivars().scheduler.borrow_mut().stop();
         // Don't update the timer while we're stopped
         unsafe { self.timer().setFireDate(&NSDate::distantFuture()) };
+        if let Some(display_link) = self.ivars().display_link.borrow_mut().take() {
+            unsafe { display_link.invalidate() };
+        }
+        // Renders requested while playing are done right away instead
+        if self.ivars().scheduler.borrow_mut().take_render_request() {
+            self.layer().setNeedsDisplay();
+        }
+    }
+
+    /// Render once the display refreshes, or right away if not using a
+    /// display link.
+    fn request_render(&self) {
+        if self.ivars().display_link.borrow().is_some() {
+            self.ivars().scheduler.borrow_mut().request_render();
+        } else {
+            self.layer().setNeedsDisplay();
+        }
+    }
+
+    /// Run a single frame, used when stepping through a paused movie.
+    pub fn step_frame(&self) {
+        let needs_render = self.with_player(|player| {
+            player.run_frame();
+            player.needs_render()
+        });
+        if needs_render == Some(true) {
+            self.request_render();
+        }
     }
 
     fn timer_fire(&self) {
-        let last_frame_time = self
-            .ivars()
-            .last_frame_time
-            .get()
-            .expect("initialized last frame time");
-        let new_time = Instant::now();
-        let dt = new_time.duration_since(last_frame_time).as_micros();
-        self.ivars().last_frame_time.set(Some(new_time));
-        tracing::trace!("timer fire: {:?}", dt as f64 / 1000000.0);
-
-        let mut player_lock = self.player_lock();
-
-        player_lock.tick(dt as f64 / 1000.0);
-        // FIXME: The instant that `time_til_next_frame` is relative to isn't
-        // defined, so we have to assume that it's roughly relative to "now".
-        let next_fire = unsafe {
-            NSDate::dateWithTimeIntervalSinceNow(player_lock.time_til_next_frame().as_secs_f64())
+        // Stopped, e.g. because the player was removed while the timer was
+        // about to fire.
+        let Some(dt) = self.ivars().scheduler.borrow_mut().tick() else {
+            return;
+        };
+        tracing::trace!("timer fire: {:?}", dt.as_secs_f64());
+
+        // The scheduler must not be borrowed here, a panic in the player
+        // stops it.
+        let res = self.with_player(|player| {
+            player.tick(dt.as_secs_f64() * 1000.0);
+            (player.time_til_next_frame(), player.needs_render())
+        });
+        let Some((time_til_next_frame, needs_render)) = res else {
+            return;
         };
-        unsafe { self.timer().setFireDate(&next_fire) };
 
-        if player_lock.needs_render() {
-            self.layer().setNeedsDisplay();
+        let scheduler = self.ivars().scheduler.borrow();
+        if let Some(next_tick) = scheduler.next_tick(time_til_next_frame) {
+            let delay = scheduler.time_until(next_tick);
+            let next_fire = unsafe { NSDate::dateWithTimeIntervalSinceNow(delay.as_secs_f64()) };
+            unsafe { self.timer().setFireDate(&next_fire) };
+        }
+        drop(scheduler);
+
+        if needs_render {
+            self.request_render();
         }
     }
 
-    fn handle_touches(&self, touches: &NSSet<UITouch>) -> bool {
-        let mut player_lock = self.player_lock();
+    fn display_link_fire(&self) {
+        if self.ivars().scheduler.borrow_mut().take_render_request() {
+            self.with_player(|player| player.render());
+        }
+    }
 
+    fn handle_touches(&self, touches: &NSSet<UITouch>) -> bool {
         // Flash only supports one touch at a time, so we intentially don't set
         // `multipleTouchEnabled`, and don't have to do check all touches here.
-        let touch = unsafe { touches.anyObject().expect("touches must contain a touch") };
+        let Some(touch) = (unsafe { touches.anyObject() }) else {
+            return false;
+        };
 
         let point = touch.locationInView(Some(self));
         let scale_factor = self.contentScaleFactor();
@@ -393,36 +506,42 @@ impl PlayerView {
         // We don't know which button was pressed in UIKit.
         let button = MouseButton::Left;
 
-        let event_handled = match touch.phase() {
-            UITouchPhase::Began => {
-                player_lock.set_mouse_in_stage(true);
-                player_lock.handle_event(PlayerEvent::MouseDown {
-                    x,
-                    y,
-                    button,
-                    // We always know whether a click was a double click or not.
-                    index: Some(touch.tapCount()),
-                })
-            }
-            UITouchPhase::Moved => {
-                player_lock.set_mouse_in_stage(true);
-                player_lock.handle_event(PlayerEvent::MouseMove { x, y })
-            }
-            UITouchPhase::Ended => {
-                player_lock.set_mouse_in_stage(true);
-                let up_handled = player_lock.handle_event(PlayerEvent::MouseUp { x, y, button });
-                player_lock.set_mouse_in_stage(false);
-                up_handled || player_lock.handle_event(PlayerEvent::MouseLeave)
-            }
-            UITouchPhase::Cancelled => {
-                player_lock.set_mouse_in_stage(true);
-                player_lock.handle_event(PlayerEvent::MouseLeave)
-            }
-            _ => return false,
-        };
+        let res = self.with_player(|player| {
+            let event_handled = match touch.phase() {
+                UITouchPhase::Began => {
+                    player.set_mouse_in_stage(true);
+                    player.handle_event(PlayerEvent::MouseDown {
+                        x,
+                        y,
+                        button,
+                        // We always know whether a click was a double click or not.
+                        index: Some(touch.tapCount()),
+                    })
+                }
+                UITouchPhase::Moved => {
+                    player.set_mouse_in_stage(true);
+                    player.handle_event(PlayerEvent::MouseMove { x, y })
+                }
+                UITouchPhase::Ended => {
+                    player.set_mouse_in_stage(true);
+                    let up_handled = player.handle_event(PlayerEvent::MouseUp { x, y, button });
+                    player.set_mouse_in_stage(false);
+                    up_handled || player.handle_event(PlayerEvent::MouseLeave)
+                }
+                UITouchPhase::Cancelled => {
+                    player.set_mouse_in_stage(true);
+                    player.handle_event(PlayerEvent::MouseLeave)
+                }
+                _ => return None,
+            };
+            Some((event_handled, player.needs_render()))
+        });
 
-        if player_lock.needs_render() {
-            self.layer().setNeedsDisplay();
+        let Some(Some((event_handled, needs_render))) = res else {
+            return false;
+        };
+        if needs_render {
+            self.request_render();
         }
 
         event_handled
@@ -430,9 +549,8 @@ impl PlayerView {
 
     fn handle_presses(&self, presses: &NSSet<UIPress>) -> bool {
         let mtm = MainThreadMarker::from(self);
-        let mut player_lock = self.player_lock();
 
-        let mut handled = false;
+        let mut events = vec![];
         for press in presses {
             // TODO: Consider press.r#type()
             let Some(key) = (unsafe { press.key(mtm) }) else {
@@ -454,10 +572,15 @@ impl PlayerView {
                 }
                 _ => continue,
             };
-
-            handled |= player_lock.handle_event(event);
+            events.push(event);
         }
-        handled
+
+        self.with_player(|player| {
+            events
+                .into_iter()
+                .fold(false, |handled, event| player.handle_event(event) | handled)
+        })
+        .unwrap_or(false)
     }
 }
 
@@ -467,6 +590,9 @@ impl Drop for PlayerView {
         if let Some(timer) = self.ivars().timer.get() {
             unsafe { timer.invalidate() };
         }
+        if let Some(display_link) = self.ivars().display_link.borrow_mut().take() {
+            unsafe { display_link.invalidate() };
+        }
     }
 }
 
@@ -0,0 +1,40 @@
+//! App-wide preferences.
+//!
+//! These are stored in `NSUserDefaults`, and edited from the Settings app,
+//! see `Settings.bundle`.
+use objc2_foundation::{ns_string, NSUserDefaults};
+
+/// What to do with files opened from other apps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OpenedFileAction {
+    /// Ask each time.
+    #[default]
+    Ask,
+    /// Play without importing, the user is offered to import it afterwards.
+    Play,
+    /// Import into the library, and play from there.
+    Import,
+}
+
+impl OpenedFileAction {
+    pub fn load() -> Self {
+        let defaults = unsafe { NSUserDefaults::standardUserDefaults() };
+        let value = unsafe { defaults.stringForKey(ns_string!("opened_file_action")) };
+        match value.map(|value| value.to_string()).as_deref() {
+            Some("play") => Self::Play,
+            Some("import") => Self::Import,
+            Some("ask") | None => Self::Ask,
+            Some(value) => {
+                tracing::warn!(value, "unknown opened_file_action");
+                Self::Ask
+            }
+        }
+    }
+}
+
+/// Whether to render in sync with the display, see
+/// [`PlayerView::set_uses_display_link`](crate::PlayerView::set_uses_display_link).
+pub fn sync_rendering_to_display() -> bool {
+    let defaults = unsafe { NSUserDefaults::standardUserDefaults() };
+    unsafe { defaults.boolForKey(ns_string!("sync_rendering_to_display")) }
+}
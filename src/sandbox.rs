@@ -0,0 +1,162 @@
+//! Restricts which local files a movie may read.
+use std::fs::File;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+/// A set of directories that a movie may open files from.
+///
+/// Paths are checked both before and after resolving symlinks, so a symlink
+/// inside a root that points outside of it is rejected as well.
+#[derive(Debug, Clone, Default)]
+pub struct FileSandbox {
+    /// The roots with symlinks resolved.
+    roots: Vec<PathBuf>,
+    /// The roots as given, since the paths that movies ask for usually go
+    /// through the same symlinks, e.g. `/var` instead of `/private/var`.
+    given_roots: Vec<PathBuf>,
+}
+
+impl FileSandbox {
+    /// Roots that don't exist are skipped, nothing can be opened in them.
+    pub fn new(roots: impl IntoIterator<Item = PathBuf>) -> Self {
+        let mut sandbox = Self::default();
+        for root in roots {
+            match root.canonicalize() {
+                Ok(resolved) => {
+                    sandbox.roots.push(resolved);
+                    sandbox.given_roots.push(root);
+                }
+                Err(err) => tracing::debug!(?root, "skipping sandbox root: {err}"),
+            }
+        }
+        sandbox
+    }
+
+    /// Check that `path` is inside one of the roots, and return the resolved
+    /// path if it is.
+    pub fn resolve(&self, path: &Path) -> io::Result<PathBuf> {
+        let denied = |reason: &str| {
+            tracing::warn!(?path, reason, "denied file access");
+            io::Error::new(io::ErrorKind::PermissionDenied, reason.to_string())
+        };
+
+        if path
+            .components()
+            .any(|component| component == Component::ParentDir)
+        {
+            return Err(denied("path contains `..`"));
+        }
+        // Relative paths would be resolved against the working directory.
+        if !path.is_absolute() {
+            return Err(denied("path is not absolute"));
+        }
+
+        // Check the path as given before touching the file system, so that
+        // the error doesn't tell whether files outside of the sandbox exist.
+        // `starts_with` compares components, which ignores `.` and repeated
+        // separators.
+        let lexically_inside = self
+            .given_roots
+            .iter()
+            .chain(&self.roots)
+            .any(|root| path.starts_with(root));
+        if !lexically_inside {
+            return Err(denied("path is outside of the sandbox"));
+        }
+
+        let resolved = path.canonicalize()?;
+        if self.roots.iter().any(|root| resolved.starts_with(root)) {
+            Ok(resolved)
+        } else {
+            Err(denied("path is outside of the sandbox"))
+        }
+    }
+
+    pub fn open(&self, path: &Path) -> io::Result<File> {
+        File::open(self.resolve(path)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::TempDir;
+
+    use super::*;
+
+    /// A sandbox rooted at `root`, next to a file outside of it.
+    fn sandbox() -> (TempDir, FileSandbox) {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("root")).unwrap();
+        fs::write(dir.path().join("root").join("movie.swf"), "inside").unwrap();
+        fs::write(dir.path().join("secret.txt"), "outside").unwrap();
+        let sandbox = FileSandbox::new([dir.path().join("root")]);
+        (dir, sandbox)
+    }
+
+    fn assert_denied(res: io::Result<PathBuf>) {
+        assert_eq!(res.unwrap_err().kind(), io::ErrorKind::PermissionDenied);
+    }
+
+    #[test]
+    fn inside() {
+        let (dir, sandbox) = sandbox();
+        let path = dir.path().join("root").join(".").join("movie.swf");
+        let resolved = sandbox.resolve(&path).unwrap();
+        assert_eq!(fs::read_to_string(resolved).unwrap(), "inside");
+    }
+
+    #[test]
+    fn missing_inside() {
+        let (dir, sandbox) = sandbox();
+        let res = sandbox.resolve(&dir.path().join("root").join("missing.swf"));
+        assert_eq!(res.unwrap_err().kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn parent_dir() {
+        let (dir, sandbox) = sandbox();
+        assert_denied(sandbox.resolve(&dir.path().join("root").join("..").join("secret.txt")));
+        // Even if it would end up inside
+        assert_denied(sandbox.resolve(&dir.path().join("root").join("..").join("root")));
+    }
+
+    #[test]
+    fn relative() {
+        let (_dir, sandbox) = sandbox();
+        assert_denied(sandbox.resolve(Path::new("movie.swf")));
+        assert_denied(sandbox.resolve(Path::new("root/movie.swf")));
+    }
+
+    #[test]
+    fn absolute_outside() {
+        let (dir, sandbox) = sandbox();
+        assert_denied(sandbox.resolve(&dir.path().join("secret.txt")));
+        // Missing files are denied the same way as existing ones
+        assert_denied(sandbox.resolve(&dir.path().join("missing.txt")));
+        // A root is not a prefix of its siblings
+        fs::create_dir(dir.path().join("root2")).unwrap();
+        assert_denied(sandbox.resolve(&dir.path().join("root2")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlink_escape() {
+        let (dir, sandbox) = sandbox();
+        let link = dir.path().join("root").join("link.txt");
+        std::os::unix::fs::symlink(dir.path().join("secret.txt"), &link).unwrap();
+        assert_denied(sandbox.resolve(&link));
+
+        let link_dir = dir.path().join("root").join("link");
+        std::os::unix::fs::symlink(dir.path(), &link_dir).unwrap();
+        assert_denied(sandbox.resolve(&link_dir.join("secret.txt")));
+    }
+
+    #[test]
+    fn missing_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let sandbox = FileSandbox::new([dir.path().join("missing")]);
+        assert_denied(sandbox.resolve(&dir.path().join("missing").join("movie.swf")));
+    }
+}
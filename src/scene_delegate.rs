@@ -1,15 +1,36 @@
 use std::cell::Cell;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::ptr::{self, NonNull};
 
+use block2::{Block, RcBlock};
 use objc2::rc::{Allocated, Retained};
 use objc2::{declare_class, msg_send_id, mutability, ClassType, DeclaredClass};
-use objc2_foundation::NSObjectProtocol;
+use objc2_foundation::{ns_string, MainThreadMarker, NSObjectProtocol, NSSet, NSString, NSURL};
 use objc2_ui_kit::{
-    UIResponder, UIScene, UISceneConnectionOptions, UISceneDelegate, UISceneSession, UIWindow,
-    UIWindowSceneDelegate,
+    UIAlertAction, UIAlertActionStyle, UIAlertController, UIAlertControllerStyle,
+    UINavigationController, UIOpenURLContext, UIResponder, UIScene, UISceneConnectionOptions,
+    UISceneDelegate, UISceneSession, UIViewController, UIWindow, UIWindowSceneDelegate,
 };
 
+use crate::app_delegate::AppDelegate;
+use crate::deep_link::{self, parse_deep_link, DeepLink};
+use crate::import::import_path;
+use crate::launch::Launch;
+use crate::library::Library;
+use crate::library_controller::{library_controllers, reload_library};
+use crate::player_controller::PlayerController;
+use crate::preferences::OpenedFileAction;
+
+const NO_LIBRARY: &str = "The library is unavailable, since there is no data directory.";
+
 pub struct Ivars {
     window: Cell<Option<Retained<UIWindow>>>,
+    /// Whether the scene is active, and thereby on screen.
+    active: Cell<bool>,
+    /// A URL that was opened before the scene became active, see
+    /// [`SceneDelegate::open_url_context`].
+    pending_url_context: Cell<Option<Retained<UIOpenURLContext>>>,
 }
 
 declare_class!(
@@ -30,7 +51,11 @@ declare_class!(
         #[method_id(init)]
         fn init(this: Allocated<Self>) -> Retained<Self> {
             tracing::info!("init scene");
-            let this = this.set_ivars(Ivars { window: Cell::new(None), });
+            let this = this.set_ivars(Ivars {
+                window: Cell::new(None),
+                active: Cell::new(false),
+                pending_url_context: Cell::new(None),
+            });
             unsafe { msg_send_id![super(this), init] }
         }
     }
@@ -44,12 +69,29 @@ declare_class!(
             &self,
             _scene: &UIScene,
             _session: &UISceneSession,
-            _connection_options: &UISceneConnectionOptions,
+            connection_options: &UISceneConnectionOptions,
         ) {
             tracing::info!("scene:willConnectToSession:options:");
             // Use this method to optionally configure and attach the UIWindow `window` to the provided UIWindowScene `scene`.
             // If using a storyboard, the `window` property will automatically be initialized and attached to the scene.
             // This delegate does not imply the connecting scene or session are new (see `application:configurationForConnectingSceneSession` instead).
+
+            if let (Some(navigation_controller), Some(library)) =
+                (self.navigation_controller(), self.library())
+            {
+                for library_controller in library_controllers(&navigation_controller) {
+                    library_controller.set_library(library.clone());
+                }
+            }
+
+            // Files opened from other apps while we weren't running
+            self.open_url_contexts(&unsafe { connection_options.URLContexts() });
+        }
+
+        #[method(scene:openURLContexts:)]
+        fn scene_openURLContexts(&self, _scene: &UIScene, url_contexts: &NSSet<UIOpenURLContext>) {
+            tracing::info!("scene:openURLContexts:");
+            self.open_url_contexts(url_contexts);
         }
 
         #[method(sceneDidDisconnect:)]
@@ -66,6 +108,16 @@ declare_class!(
             tracing::info!("sceneDidBecomeActive:");
             // Called when the scene has moved from an inactive state to an active state.
             // Use this method to restart any tasks that were paused (or not yet started) when the scene was inactive.
+            //
+            // This is also called after entering the foreground, so we resume here.
+            if let Some(player_controller) = self.player_controller() {
+                player_controller.resume();
+            }
+
+            self.ivars().active.set(true);
+            if let Some(context) = self.ivars().pending_url_context.take() {
+                self.open_url_context(&context);
+            }
         }
 
         #[method(sceneWillResignActive:)]
@@ -73,6 +125,10 @@ declare_class!(
             tracing::info!("sceneWillResignActive:");
             // Called when the scene will move from an active state to an inactive state.
             // This may occur due to temporary interruptions (ex. an incoming phone call).
+            self.ivars().active.set(false);
+            if let Some(player_controller) = self.player_controller() {
+                player_controller.suspend();
+            }
         }
 
         #[method(sceneWillEnterForeground:)]
@@ -88,6 +144,9 @@ declare_class!(
             // Called as the scene transitions from the foreground to the background.
             // Use this method to save data, release shared resources, and store enough scene-specific state information
             // to restore the scene back to its current state.
+            if let Some(player_controller) = self.player_controller() {
+                player_controller.flush_shared_objects();
+            }
         }
     }
 
@@ -107,6 +166,348 @@ declare_class!(
     }
 );
 
+impl SceneDelegate {
+    fn root_view_controller(&self) -> Option<Retained<UIViewController>> {
+        let window = self.ivars().window.take();
+        self.ivars().window.set(window.clone());
+        window?.rootViewController()
+    }
+
+    fn navigation_controller(&self) -> Option<Retained<UINavigationController>> {
+        let controller = self.root_view_controller()?;
+        if controller.isKindOfClass(UINavigationController::class()) {
+            Some(unsafe { Retained::cast(controller) })
+        } else {
+            None
+        }
+    }
+
+    /// The library created by the application delegate at startup.
+    fn library(&self) -> Option<Library> {
+        let mtm = MainThreadMarker::from(self);
+        AppDelegate::shared(mtm)?.library().cloned()
+    }
+
+    /// The player that is currently shown in the window, if any.
+    fn player_controller(&self) -> Option<Retained<PlayerController>> {
+        let mut controller = self.root_view_controller()?;
+        if controller.isKindOfClass(UINavigationController::class()) {
+            let navigation_controller =
+                unsafe { Retained::cast::<UINavigationController>(controller) };
+            controller = unsafe { navigation_controller.topViewController() }?;
+        }
+        if controller.isKindOfClass(PlayerController::class()) {
+            // SAFETY: Just checked that the controller is a `PlayerController`
+            Some(unsafe { Retained::cast(controller) })
+        } else {
+            None
+        }
+    }
+}
+
+impl SceneDelegate {
+    fn open_url_contexts(&self, url_contexts: &NSSet<UIOpenURLContext>) {
+        // We can only show one movie at a time
+        let Some(context) = url_contexts.anyObject() else {
+            return;
+        };
+        if url_contexts.count() > 1 {
+            tracing::warn!("opening only one of {} URLs", url_contexts.count());
+        }
+        self.open_url_context(&context);
+    }
+
+    /// Open a file or link from another app.
+    ///
+    /// URLs that arrive before the scene is active, e.g. when they launched
+    /// the app, are opened once it is, since UIKit drops alerts that are
+    /// presented before the window is on screen.
+    fn open_url_context(&self, context: &UIOpenURLContext) {
+        let url = unsafe { context.URL() };
+        if !self.ivars().active.get() {
+            tracing::info!(url = ?unsafe { url.absoluteString() }, "opening URL once the scene is active");
+            self.ivars().pending_url_context.set(Some(context.retain()));
+            return;
+        }
+
+        let Some(navigation_controller) = self.navigation_controller() else {
+            tracing::error!(url = ?unsafe { url.absoluteString() }, "no navigation controller to open URL in");
+            return;
+        };
+
+        let scheme = unsafe { url.scheme() }.map(|scheme| scheme.to_string());
+        if scheme.as_deref() == Some(deep_link::SCHEME) {
+            let link = unsafe { url.absoluteString() }
+                .map(|link| link.to_string())
+                .unwrap_or_default();
+            open_deep_link(&navigation_controller, self.library().as_ref(), &link);
+            return;
+        }
+
+        let Some(path) = file_url_path(&url) else {
+            tracing::warn!(url = ?unsafe { url.absoluteString() }, "unsupported URL");
+            return;
+        };
+
+        tracing::info!(?path, "opening file");
+        let library = self.library();
+        match OpenedFileAction::load() {
+            OpenedFileAction::Ask => ask_open_file(&navigation_controller, library, path),
+            OpenedFileAction::Play => play_file(&navigation_controller, library.as_ref(), &path),
+            OpenedFileAction::Import => {
+                import_file(&navigation_controller, library.as_ref(), &path)
+            }
+        }
+    }
+}
+
+fn file_url_path(url: &NSURL) -> Option<PathBuf> {
+    if !unsafe { url.isFileURL() } {
+        return None;
+    }
+    let path = unsafe { url.path() }?;
+    Some(PathBuf::from(path.to_string()))
+}
+
+fn open_deep_link(
+    navigation_controller: &UINavigationController,
+    library: Option<&Library>,
+    link: &str,
+) {
+    tracing::info!(link, "opening link");
+    let launch = match parse_deep_link(link) {
+        Ok(DeepLink::LibraryItem(id)) => {
+            let Some(library) = library else {
+                return show_error(navigation_controller, "Could not open link", NO_LIBRARY);
+            };
+            match library.load_item(id) {
+                Ok(item) => Launch::from_library(library, &item),
+                Err(err) => {
+                    let message = format!("No movie with id {id} in the library: {err}");
+                    return show_error(navigation_controller, "Could not open link", &message);
+                }
+            }
+        }
+        Ok(DeepLink::Open { url, parameters }) => {
+            let launch = Launch::remote(url, parameters);
+            return confirm_open_remote(navigation_controller, library.cloned(), launch);
+        }
+        Err(err) => {
+            return show_error(navigation_controller, "Invalid link", &format!("{err}"));
+        }
+    };
+    show_player(navigation_controller, library, launch);
+}
+
+/// Ask the user before playing a movie from the internet, since any website
+/// can open a link.
+fn confirm_open_remote(
+    navigation_controller: &UINavigationController,
+    library: Option<Library>,
+    launch: Launch,
+) {
+    let mtm = MainThreadMarker::from(navigation_controller);
+    let message = NSString::from_str(&format!(
+        "A link wants to play a movie from the internet:\n{}",
+        launch.info.url
+    ));
+
+    let play_block = {
+        let navigation_controller = navigation_controller.retain();
+        RcBlock::new(move |_: NonNull<UIAlertAction>| {
+            show_player(&navigation_controller, library.as_ref(), launch.clone());
+        })
+    };
+    let play_ptr: *const Block<_> = &*play_block;
+
+    unsafe {
+        let alert = UIAlertController::alertControllerWithTitle_message_preferredStyle(
+            Some(ns_string!("Play Movie?")),
+            Some(&message),
+            UIAlertControllerStyle::Alert,
+            mtm,
+        );
+        alert.addAction(&UIAlertAction::actionWithTitle_style_handler(
+            Some(ns_string!("Cancel")),
+            UIAlertActionStyle::Cancel,
+            ptr::null_mut(),
+            mtm,
+        ));
+        alert.addAction(&UIAlertAction::actionWithTitle_style_handler(
+            Some(ns_string!("Play")),
+            UIAlertActionStyle::Default,
+            play_ptr.cast_mut(),
+            mtm,
+        ));
+        navigation_controller.presentViewController_animated_completion(&alert, true, None);
+    }
+}
+
+fn ask_open_file(
+    navigation_controller: &UINavigationController,
+    library: Option<Library>,
+    path: PathBuf,
+) {
+    let mtm = MainThreadMarker::from(navigation_controller);
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let title = NSString::from_str(&format!("Open \"{name}\""));
+
+    let play_block = {
+        let navigation_controller = navigation_controller.retain();
+        let library = library.clone();
+        let path = path.clone();
+        RcBlock::new(move |_: NonNull<UIAlertAction>| {
+            play_file(&navigation_controller, library.as_ref(), &path)
+        })
+    };
+    let play_ptr: *const Block<_> = &*play_block;
+    let import_block = {
+        let navigation_controller = navigation_controller.retain();
+        RcBlock::new(move |_: NonNull<UIAlertAction>| {
+            import_file(&navigation_controller, library.as_ref(), &path)
+        })
+    };
+    let import_ptr: *const Block<_> = &*import_block;
+
+    unsafe {
+        let alert = UIAlertController::alertControllerWithTitle_message_preferredStyle(
+            Some(&title),
+            Some(ns_string!(
+                "You can choose what happens to opened files in the Settings app."
+            )),
+            UIAlertControllerStyle::Alert,
+            mtm,
+        );
+        alert.addAction(&UIAlertAction::actionWithTitle_style_handler(
+            Some(ns_string!("Play Without Importing")),
+            UIAlertActionStyle::Default,
+            play_ptr.cast_mut(),
+            mtm,
+        ));
+        alert.addAction(&UIAlertAction::actionWithTitle_style_handler(
+            Some(ns_string!("Import into Library")),
+            UIAlertActionStyle::Default,
+            import_ptr.cast_mut(),
+            mtm,
+        ));
+        alert.addAction(&UIAlertAction::actionWithTitle_style_handler(
+            Some(ns_string!("Cancel")),
+            UIAlertActionStyle::Cancel,
+            ptr::null_mut(),
+            mtm,
+        ));
+        navigation_controller.presentViewController_animated_completion(&alert, true, None);
+    }
+}
+
+fn play_file(
+    navigation_controller: &UINavigationController,
+    library: Option<&Library>,
+    path: &Path,
+) {
+    match Launch::transient(path) {
+        Ok(launch) => {
+            let player_controller = show_player(navigation_controller, library, launch);
+            // Kept while playing, since the user may still import it
+            let path = path.to_path_buf();
+            player_controller.set_close_handler(move || remove_inbox_file(&path));
+        }
+        Err(err) => {
+            remove_inbox_file(path);
+            show_error(
+                navigation_controller,
+                "Could not open file",
+                &format!("{err}"),
+            );
+        }
+    }
+}
+
+fn import_file(
+    navigation_controller: &UINavigationController,
+    library: Option<&Library>,
+    path: &Path,
+) {
+    let Some(library) = library else {
+        return show_error(navigation_controller, "Could not open file", NO_LIBRARY);
+    };
+    match import_path(library, path) {
+        Ok(item) => {
+            tracing::info!(id = %item.id, "imported opened file");
+            remove_inbox_file(path);
+            reload_library(navigation_controller);
+            let launch = Launch::from_library(library, &item);
+            show_player(navigation_controller, Some(library), launch);
+        }
+        Err(err) => show_error(
+            navigation_controller,
+            "Could not open file",
+            &format!("{err}"),
+        ),
+    }
+}
+
+/// Files from other apps are copied into `Documents/Inbox`, since we don't
+/// open documents in place. Once imported or played, the copy isn't needed
+/// anymore.
+fn remove_inbox_file(path: &Path) {
+    let in_inbox = path
+        .parent()
+        .is_some_and(|parent| parent.file_name() == Some("Inbox".as_ref()));
+    if in_inbox {
+        if let Err(err) = fs::remove_file(path) {
+            tracing::warn!(?path, "failed removing inbox file: {err}");
+        }
+    }
+}
+
+fn show_player(
+    navigation_controller: &UINavigationController,
+    library: Option<&Library>,
+    launch: Launch,
+) -> Retained<PlayerController> {
+    let mtm = MainThreadMarker::from(navigation_controller);
+    let player_controller = PlayerController::new(mtm, launch);
+    if let Some(library) = library {
+        player_controller.set_library(library.clone());
+    }
+    for controller in unsafe { navigation_controller.viewControllers() } {
+        if controller.isKindOfClass(PlayerController::class()) {
+            // SAFETY: Just checked that the controller is a `PlayerController`
+            let old_player = unsafe { Retained::cast::<PlayerController>(controller) };
+            old_player.set_replaced();
+        }
+    }
+    unsafe {
+        navigation_controller.popToRootViewControllerAnimated(false);
+        navigation_controller.pushViewController_animated(&player_controller, true);
+    }
+    player_controller
+}
+
+fn show_error(navigation_controller: &UINavigationController, title: &str, message: &str) {
+    tracing::error!("{title}: {message}");
+    let mtm = MainThreadMarker::from(navigation_controller);
+    unsafe {
+        let alert = UIAlertController::alertControllerWithTitle_message_preferredStyle(
+            Some(&NSString::from_str(title)),
+            Some(&NSString::from_str(message)),
+            UIAlertControllerStyle::Alert,
+            mtm,
+        );
+        alert.addAction(&UIAlertAction::actionWithTitle_style_handler(
+            Some(ns_string!("OK")),
+            UIAlertActionStyle::Default,
+            ptr::null_mut(),
+            mtm,
+        ));
+        navigation_controller.presentViewController_animated_completion(&alert, true, None);
+    }
+}
+
 impl Drop for SceneDelegate {
     fn drop(&mut self) {
         tracing::info!("drop scene");
@@ -0,0 +1,261 @@
+//! Deciding when to tick and render the player.
+//!
+//! This doesn't depend on UIKit, the player view drives it from a timer.
+use std::time::{Duration, Instant};
+
+/// The source of the current time.
+///
+/// The scheduler only reads the time through this, so that it can be driven
+/// by something else than the system clock.
+pub trait Clock {
+    fn now(&self) -> Instant;
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// Keeps track of the time between ticks, and of requested renders.
+///
+/// Ticking advances the movie and its timers, and may happen more or less
+/// often than the display refreshes, so renders are only requested when
+/// ticking, and done separately.
+#[derive(Debug)]
+pub struct FrameScheduler<C = SystemClock> {
+    clock: C,
+    /// When the player was last ticked, or `None` while stopped.
+    last_tick: Option<Instant>,
+    render_requested: bool,
+}
+
+impl Default for FrameScheduler {
+    fn default() -> Self {
+        Self::with_clock(SystemClock)
+    }
+}
+
+impl<C: Clock> FrameScheduler<C> {
+    pub fn with_clock(clock: C) -> Self {
+        Self {
+            clock,
+            last_tick: None,
+            render_requested: false,
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.last_tick.is_some()
+    }
+
+    /// Start counting time from now.
+    ///
+    /// The time while stopped is never passed to the player, so it doesn't
+    /// try to catch up on frames after being paused.
+    pub fn start(&mut self) {
+        if self.last_tick.is_none() {
+            self.last_tick = Some(self.clock.now());
+        }
+    }
+
+    pub fn stop(&mut self) {
+        self.last_tick = None;
+    }
+
+    /// Count time from now, if running, e.g. because the player was
+    /// replaced by one that was just created.
+    pub fn reset(&mut self) {
+        if self.last_tick.is_some() {
+            self.last_tick = Some(self.clock.now());
+        }
+    }
+
+    /// The time since the last tick, to pass to `Player::tick`, or `None` if
+    /// stopped.
+    ///
+    /// If the tick is late, the whole time is returned, so that the player
+    /// catches up on the frames it missed.
+    pub fn tick(&mut self) -> Option<Duration> {
+        let last_tick = self.last_tick?;
+        let now = self.clock.now();
+        self.last_tick = Some(now);
+        Some(now.saturating_duration_since(last_tick))
+    }
+
+    /// When to tick next, given `Player::time_til_next_frame` right after
+    /// the last tick.
+    ///
+    /// That is relative to the time that the player was ticked up to, not to
+    /// when it finished ticking, so the time spent running frames doesn't
+    /// accumulate as drift.
+    pub fn next_tick(&self, time_til_next_frame: Duration) -> Option<Instant> {
+        self.last_tick
+            .map(|last_tick| last_tick + time_til_next_frame)
+    }
+
+    /// How long to wait until `instant`, or zero if it has already passed.
+    pub fn time_until(&self, instant: Instant) -> Duration {
+        instant.saturating_duration_since(self.clock.now())
+    }
+
+    pub fn request_render(&mut self) {
+        self.render_requested = true;
+    }
+
+    /// Whether a render was requested since the last call.
+    pub fn take_render_request(&mut self) -> bool {
+        std::mem::take(&mut self.render_requested)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use super::*;
+
+    /// A clock that only moves when told to.
+    #[derive(Debug, Clone)]
+    struct FakeClock(Rc<Cell<Instant>>);
+
+    impl FakeClock {
+        fn new() -> Self {
+            Self(Rc::new(Cell::new(Instant::now())))
+        }
+
+        fn advance(&self, duration: Duration) {
+            self.0.set(self.0.get() + duration);
+        }
+    }
+
+    impl Clock for FakeClock {
+        fn now(&self) -> Instant {
+            self.0.get()
+        }
+    }
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    fn scheduler() -> (FakeClock, FrameScheduler<FakeClock>) {
+        let clock = FakeClock::new();
+        (clock.clone(), FrameScheduler::with_clock(clock))
+    }
+
+    #[test]
+    fn stopped_by_default() {
+        let (clock, mut scheduler) = scheduler();
+        assert!(!scheduler.is_running());
+        clock.advance(ms(100));
+        assert_eq!(scheduler.tick(), None);
+        assert_eq!(scheduler.next_tick(ms(10)), None);
+    }
+
+    #[test]
+    fn tick_returns_elapsed_time() {
+        let (clock, mut scheduler) = scheduler();
+        scheduler.start();
+        clock.advance(ms(16));
+        assert_eq!(scheduler.tick(), Some(ms(16)));
+        clock.advance(ms(17));
+        assert_eq!(scheduler.tick(), Some(ms(17)));
+    }
+
+    #[test]
+    fn late_tick_catches_up() {
+        let (clock, mut scheduler) = scheduler();
+        scheduler.start();
+        // Several frames are missed, e.g. because the main thread was busy
+        clock.advance(ms(250));
+        assert_eq!(scheduler.tick(), Some(ms(250)));
+    }
+
+    #[test]
+    fn next_tick_is_anchored_to_last_tick() {
+        let (clock, mut scheduler) = scheduler();
+        scheduler.start();
+        clock.advance(ms(20));
+        scheduler.tick();
+        let ticked_at = clock.now();
+
+        // Running the frames takes a while
+        clock.advance(ms(5));
+        let next = scheduler.next_tick(ms(16)).unwrap();
+        assert_eq!(next, ticked_at + ms(16));
+        assert_eq!(scheduler.time_until(next), ms(11));
+
+        // The next tick passes the time since the last one, including the
+        // time spent running frames.
+        clock.advance(ms(11));
+        assert_eq!(scheduler.tick(), Some(ms(16)));
+    }
+
+    #[test]
+    fn time_until_passed_instant_is_zero() {
+        let (clock, scheduler) = scheduler();
+        let instant = clock.now();
+        clock.advance(ms(5));
+        assert_eq!(scheduler.time_until(instant), Duration::ZERO);
+    }
+
+    #[test]
+    fn paused_time_is_skipped() {
+        let (clock, mut scheduler) = scheduler();
+        scheduler.start();
+        clock.advance(ms(10));
+        scheduler.stop();
+        assert!(!scheduler.is_running());
+        assert_eq!(scheduler.tick(), None);
+
+        clock.advance(Duration::from_secs(60));
+        scheduler.start();
+        clock.advance(ms(16));
+        assert_eq!(scheduler.tick(), Some(ms(16)));
+    }
+
+    #[test]
+    fn start_while_running_keeps_last_tick() {
+        let (clock, mut scheduler) = scheduler();
+        scheduler.start();
+        clock.advance(ms(10));
+        scheduler.start();
+        clock.advance(ms(6));
+        assert_eq!(scheduler.tick(), Some(ms(16)));
+    }
+
+    #[test]
+    fn reset_after_set_player() {
+        let (clock, mut scheduler) = scheduler();
+        scheduler.start();
+        // Creating the new player took a while
+        clock.advance(ms(500));
+        scheduler.reset();
+        clock.advance(ms(16));
+        assert_eq!(scheduler.tick(), Some(ms(16)));
+    }
+
+    #[test]
+    fn reset_while_stopped() {
+        let (clock, mut scheduler) = scheduler();
+        scheduler.reset();
+        assert!(!scheduler.is_running());
+        clock.advance(ms(16));
+        assert_eq!(scheduler.tick(), None);
+    }
+
+    #[test]
+    fn render_requests() {
+        let (_clock, mut scheduler) = scheduler();
+        assert!(!scheduler.take_render_request());
+        scheduler.request_render();
+        scheduler.request_render();
+        assert!(scheduler.take_render_request());
+        assert!(!scheduler.take_render_request());
+    }
+}
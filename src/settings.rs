@@ -0,0 +1,603 @@
+//! Reading and writing bundle information in the `ruffle-bundle.toml` format.
+//!
+//! `ruffle_frontend_utils` only knows how to parse this format, so we
+//! provide the writing half ourselves. The per-item `settings.toml` uses the
+//! same format, so that it can be read back with the same parser, with an
+//! additional `[ios]` table for settings that only this app knows about.
+use std::time::Duration;
+
+use ruffle_core::backend::navigator::{OpenURLMode, SocketMode};
+use ruffle_core::config::Letterbox;
+use ruffle_core::{LoadBehavior, PlayerRuntime, StageAlign, StageScaleMode};
+use ruffle_frontend_utils::bundle::info::{BundleInformation, BundleInformationParseError};
+use ruffle_frontend_utils::player_options::PlayerOptions;
+use ruffle_render::quality::StageQuality;
+use toml_edit::{value, Array, DocumentMut, InlineTable, Item, Table};
+
+/// The table in `settings.toml` that holds [`ItemSettings`].
+const ITEM_SETTINGS_TABLE: &str = "ios";
+
+/// Per-item settings that aren't part of the bundle format.
+///
+/// `None` means that the default should be used.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ItemSettings {
+    /// Whether the movie may open web pages. Defaults to asking the user.
+    pub open_url_mode: Option<OpenURLMode>,
+    /// Whether the movie may open TCP connections. Defaults to asking the
+    /// user.
+    pub socket_mode: Option<SocketMode>,
+    /// Addresses (`host:port`) that the user has allowed connections to.
+    pub socket_allowed: Vec<String>,
+    /// Addresses (`host:port`) that the user has denied connections to.
+    pub socket_denied: Vec<String>,
+    /// Where on the screen the movie is shown. Defaults to the safe area.
+    pub layout: Option<ViewportLayout>,
+}
+
+/// Which part of the screen the stage covers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ViewportLayout {
+    /// Inset from the notch, the home indicator and the navigation bar, so
+    /// that nothing in the movie is hidden behind them.
+    #[default]
+    SafeArea,
+    /// The whole screen, for movies that don't mind being partially hidden.
+    FullBleed,
+}
+
+pub fn read_bundle_information(
+    input: &str,
+) -> Result<BundleInformation, BundleInformationParseError> {
+    let details = BundleInformation::parse(input)?;
+    for warning in &details.warnings {
+        tracing::warn!("bundle information: {warning}");
+    }
+    Ok(details.result.take())
+}
+
+pub fn write_bundle_information(info: &BundleInformation) -> String {
+    let mut document = DocumentMut::new();
+
+    let mut bundle = Table::new();
+    bundle.insert("name", value(info.name.as_str()));
+    bundle.insert("url", value(info.url.as_str()));
+    document.insert("bundle", Item::Table(bundle));
+
+    document.insert("player", Item::Table(player_options_table(&info.player)));
+
+    document.to_string()
+}
+
+/// Read `settings.toml`, which is bundle information with an extra table for
+/// [`ItemSettings`].
+pub fn read_settings(
+    input: &str,
+) -> Result<(BundleInformation, ItemSettings), BundleInformationParseError> {
+    let Ok(mut document) = input.parse::<DocumentMut>() else {
+        // Let the bundle information parser report the error
+        return Ok((read_bundle_information(input)?, ItemSettings::default()));
+    };
+
+    let settings = match document.remove(ITEM_SETTINGS_TABLE) {
+        Some(item) => item_settings_from_item(&item),
+        None => ItemSettings::default(),
+    };
+    let info = read_bundle_information(&document.to_string())?;
+    Ok((info, settings))
+}
+
+pub fn write_settings(info: &BundleInformation, settings: &ItemSettings) -> String {
+    let mut document: DocumentMut = write_bundle_information(info)
+        .parse()
+        .expect("we just wrote valid TOML");
+
+    let table = item_settings_table(settings);
+    if !table.is_empty() {
+        document.insert(ITEM_SETTINGS_TABLE, Item::Table(table));
+    }
+
+    document.to_string()
+}
+
+/// Update an existing `settings.toml` in place.
+///
+/// Unlike [`write_settings`], this keeps keys that we don't know about, such
+/// as player options added by a newer Ruffle, along with any comments.
+pub fn update_settings(
+    existing: &str,
+    info: &BundleInformation,
+    settings: &ItemSettings,
+) -> String {
+    let Ok(mut document) = existing.parse::<DocumentMut>() else {
+        tracing::warn!("rewriting unparseable settings");
+        return write_settings(info, settings);
+    };
+
+    let mut bundle = Table::new();
+    bundle.insert("name", value(info.name.as_str()));
+    bundle.insert("url", value(info.url.as_str()));
+    merge_table(&mut document, "bundle", BUNDLE_KEYS, bundle);
+    merge_table(
+        &mut document,
+        "player",
+        PLAYER_OPTIONS_KEYS,
+        player_options_table(&info.player),
+    );
+    merge_table(
+        &mut document,
+        ITEM_SETTINGS_TABLE,
+        ITEM_SETTINGS_KEYS,
+        item_settings_table(settings),
+    );
+    if document
+        .get(ITEM_SETTINGS_TABLE)
+        .and_then(Item::as_table)
+        .is_some_and(Table::is_empty)
+    {
+        document.remove(ITEM_SETTINGS_TABLE);
+    }
+
+    document.to_string()
+}
+
+/// The keys in the `[bundle]` table that [`write_bundle_information`] writes.
+const BUNDLE_KEYS: &[&str] = &["name", "url"];
+
+/// The keys in the `[player]` table that [`player_options_table`] writes.
+const PLAYER_OPTIONS_KEYS: &[&str] = &[
+    "parameters",
+    "script_timeout",
+    "base_url",
+    "quality",
+    "align",
+    "force_align",
+    "scale_mode",
+    "force_scale_mode",
+    "upgrade_http_to_https",
+    "load_behavior",
+    "letterbox",
+    "spoof_url",
+    "referer",
+    "cookie",
+    "version",
+    "runtime",
+    "frame_rate",
+    "mock_external_interface",
+];
+
+/// The keys in the `[ios]` table that [`item_settings_table`] writes.
+const ITEM_SETTINGS_KEYS: &[&str] = &[
+    "open_url_mode",
+    "socket_mode",
+    "socket_allowed",
+    "socket_denied",
+    "layout",
+];
+
+/// Replace the known keys of the table `name` with those in `new`, leaving
+/// other keys where they are.
+fn merge_table(document: &mut DocumentMut, name: &str, known_keys: &[&str], new: Table) {
+    let item = document.entry(name).or_insert(toml_edit::table());
+    if !item.is_table() {
+        *item = toml_edit::table();
+    }
+    let table = item.as_table_mut().expect("we just made sure it's a table");
+
+    for &key in known_keys {
+        if !new.contains_key(key) {
+            table.remove(key);
+        }
+    }
+    for (key, item) in new {
+        table.insert(&key, item);
+    }
+}
+
+fn item_settings_from_item(item: &Item) -> ItemSettings {
+    let mut settings = ItemSettings::default();
+
+    if let Some(mode) = item.get("open_url_mode").and_then(Item::as_str) {
+        settings.open_url_mode = open_url_mode_from_str(mode);
+        if settings.open_url_mode.is_none() {
+            tracing::warn!(mode, "unknown open_url_mode");
+        }
+    }
+    if let Some(mode) = item.get("socket_mode").and_then(Item::as_str) {
+        settings.socket_mode = socket_mode_from_str(mode);
+        if settings.socket_mode.is_none() {
+            tracing::warn!(mode, "unknown socket_mode");
+        }
+    }
+    settings.socket_allowed = string_array(item, "socket_allowed");
+    settings.socket_denied = string_array(item, "socket_denied");
+    if let Some(layout) = item.get("layout").and_then(Item::as_str) {
+        settings.layout = layout_from_str(layout);
+        if settings.layout.is_none() {
+            tracing::warn!(layout, "unknown layout");
+        }
+    }
+
+    settings
+}
+
+fn item_settings_table(settings: &ItemSettings) -> Table {
+    let mut table = Table::new();
+
+    if let Some(mode) = settings.open_url_mode {
+        table.insert("open_url_mode", value(open_url_mode_to_str(mode)));
+    }
+    if let Some(mode) = settings.socket_mode {
+        table.insert("socket_mode", value(socket_mode_to_str(mode)));
+    }
+    if !settings.socket_allowed.is_empty() {
+        table.insert(
+            "socket_allowed",
+            value(Array::from_iter(&settings.socket_allowed)),
+        );
+    }
+    if !settings.socket_denied.is_empty() {
+        table.insert(
+            "socket_denied",
+            value(Array::from_iter(&settings.socket_denied)),
+        );
+    }
+    if let Some(layout) = settings.layout {
+        table.insert("layout", value(layout_to_str(layout)));
+    }
+
+    table
+}
+
+fn string_array(item: &Item, key: &str) -> Vec<String> {
+    let Some(array) = item.get(key).and_then(Item::as_array) else {
+        return vec![];
+    };
+    array
+        .iter()
+        .filter_map(|val| {
+            let s = val.as_str();
+            if s.is_none() {
+                tracing::warn!(key, "ignoring non-string entry");
+            }
+            s.map(String::from)
+        })
+        .collect()
+}
+
+fn player_options_table(options: &PlayerOptions) -> Table {
+    let mut table = Table::new();
+
+    if !options.parameters.is_empty() {
+        let mut parameters = InlineTable::new();
+        for (key, val) in &options.parameters {
+            parameters.insert(key, val.as_str().into());
+        }
+        table.insert("parameters", value(parameters));
+    }
+    if let Some(duration) = options.max_execution_duration {
+        table.insert("script_timeout", value(duration_to_secs(duration)));
+    }
+    if let Some(base) = &options.base {
+        table.insert("base_url", value(base.as_str()));
+    }
+    if let Some(quality) = options.quality {
+        table.insert("quality", value(quality_to_str(quality)));
+    }
+    if let Some(align) = options.align {
+        table.insert("align", value(align_to_str(align)));
+    }
+    if let Some(force_align) = options.force_align {
+        table.insert("force_align", value(force_align));
+    }
+    if let Some(scale) = options.scale {
+        table.insert("scale_mode", value(scale_to_str(scale)));
+    }
+    if let Some(force_scale) = options.force_scale {
+        table.insert("force_scale_mode", value(force_scale));
+    }
+    if let Some(upgrade_to_https) = options.upgrade_to_https {
+        table.insert("upgrade_http_to_https", value(upgrade_to_https));
+    }
+    if let Some(load_behavior) = options.load_behavior {
+        table.insert("load_behavior", value(load_behavior_to_str(load_behavior)));
+    }
+    if let Some(letterbox) = options.letterbox {
+        table.insert("letterbox", value(letterbox_to_str(letterbox)));
+    }
+    if let Some(spoof_url) = &options.spoof_url {
+        table.insert("spoof_url", value(spoof_url.as_str()));
+    }
+    if let Some(referer) = &options.referer {
+        table.insert("referer", value(referer.as_str()));
+    }
+    if let Some(cookie) = &options.cookie {
+        table.insert("cookie", value(cookie.as_str()));
+    }
+    if let Some(version) = options.player_version {
+        table.insert("version", value(i64::from(version)));
+    }
+    if let Some(runtime) = options.player_runtime {
+        table.insert("runtime", value(runtime_to_str(runtime)));
+    }
+    if let Some(frame_rate) = options.frame_rate {
+        table.insert("frame_rate", value(frame_rate));
+    }
+    if let Some(dummy_external_interface) = options.dummy_external_interface {
+        table.insert("mock_external_interface", value(dummy_external_interface));
+    }
+
+    table
+}
+
+fn duration_to_secs(duration: Duration) -> i64 {
+    duration.as_secs().try_into().unwrap_or(i64::MAX)
+}
+
+// The string representations below match what the `FromStr` implementations
+// in `ruffle_core` and `ruffle_render` accept.
+
+pub(crate) fn quality_to_str(quality: StageQuality) -> &'static str {
+    match quality {
+        StageQuality::Low => "low",
+        StageQuality::Medium => "medium",
+        StageQuality::High => "high",
+        StageQuality::Best => "best",
+        StageQuality::High8x8 => "8x8",
+        StageQuality::High8x8Linear => "8x8linear",
+        StageQuality::High16x16 => "16x16",
+        StageQuality::High16x16Linear => "16x16linear",
+    }
+}
+
+fn align_to_str(align: StageAlign) -> String {
+    let mut s = String::new();
+    if align.contains(StageAlign::TOP) {
+        s.push('T');
+    }
+    if align.contains(StageAlign::BOTTOM) {
+        s.push('B');
+    }
+    if align.contains(StageAlign::LEFT) {
+        s.push('L');
+    }
+    if align.contains(StageAlign::RIGHT) {
+        s.push('R');
+    }
+    s
+}
+
+pub(crate) fn scale_to_str(scale: StageScaleMode) -> &'static str {
+    match scale {
+        StageScaleMode::ExactFit => "exactfit",
+        StageScaleMode::NoBorder => "noborder",
+        StageScaleMode::NoScale => "noscale",
+        StageScaleMode::ShowAll => "showall",
+    }
+}
+
+pub(crate) fn load_behavior_to_str(load_behavior: LoadBehavior) -> &'static str {
+    match load_behavior {
+        LoadBehavior::Streaming => "streaming",
+        LoadBehavior::Delayed => "delayed",
+        LoadBehavior::Blocking => "blocking",
+    }
+}
+
+pub(crate) fn letterbox_to_str(letterbox: Letterbox) -> &'static str {
+    match letterbox {
+        Letterbox::Off => "off",
+        Letterbox::Fullscreen => "fullscreen",
+        Letterbox::On => "on",
+    }
+}
+
+pub(crate) fn runtime_to_str(runtime: PlayerRuntime) -> &'static str {
+    match runtime {
+        PlayerRuntime::FlashPlayer => "flash_player",
+        PlayerRuntime::AIR => "air",
+    }
+}
+
+pub(crate) fn open_url_mode_to_str(mode: OpenURLMode) -> &'static str {
+    match mode {
+        OpenURLMode::Allow => "allow",
+        OpenURLMode::Confirm => "confirm",
+        OpenURLMode::Deny => "deny",
+    }
+}
+
+fn open_url_mode_from_str(s: &str) -> Option<OpenURLMode> {
+    match s {
+        "allow" => Some(OpenURLMode::Allow),
+        "confirm" => Some(OpenURLMode::Confirm),
+        "deny" => Some(OpenURLMode::Deny),
+        _ => None,
+    }
+}
+
+pub(crate) fn socket_mode_to_str(mode: SocketMode) -> &'static str {
+    match mode {
+        SocketMode::Allow => "allow",
+        SocketMode::Ask => "ask",
+        SocketMode::Deny => "deny",
+    }
+}
+
+fn socket_mode_from_str(s: &str) -> Option<SocketMode> {
+    match s {
+        "allow" => Some(SocketMode::Allow),
+        "ask" => Some(SocketMode::Ask),
+        "deny" => Some(SocketMode::Deny),
+        _ => None,
+    }
+}
+
+fn layout_to_str(layout: ViewportLayout) -> &'static str {
+    match layout {
+        ViewportLayout::SafeArea => "safe_area",
+        ViewportLayout::FullBleed => "full_bleed",
+    }
+}
+
+fn layout_from_str(s: &str) -> Option<ViewportLayout> {
+    match s {
+        "safe_area" => Some(ViewportLayout::SafeArea),
+        "full_bleed" => Some(ViewportLayout::FullBleed),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ruffle_core::backend::navigator::{OpenURLMode, SocketMode};
+    use url::Url;
+
+    use super::*;
+
+    fn populated() -> (BundleInformation, ItemSettings) {
+        let player = PlayerOptions {
+            parameters: vec![
+                ("foo".into(), "bar".into()),
+                ("with space".into(), "a \"quoted\" value".into()),
+            ],
+            max_execution_duration: Some(Duration::from_secs(30)),
+            base: Some(Url::parse("https://example.com/base/").unwrap()),
+            quality: Some(StageQuality::High8x8Linear),
+            align: Some(StageAlign::TOP | StageAlign::LEFT),
+            force_align: Some(true),
+            scale: Some(StageScaleMode::NoBorder),
+            force_scale: Some(false),
+            upgrade_to_https: Some(true),
+            load_behavior: Some(LoadBehavior::Blocking),
+            letterbox: Some(Letterbox::Fullscreen),
+            spoof_url: Some(Url::parse("https://example.com/game.swf").unwrap()),
+            referer: Some(Url::parse("https://example.com/").unwrap()),
+            cookie: Some("session=1234".into()),
+            player_version: Some(32),
+            player_runtime: Some(PlayerRuntime::AIR),
+            frame_rate: Some(60.5),
+            dummy_external_interface: Some(true),
+            ..Default::default()
+        };
+        let info = BundleInformation {
+            name: "My Movie".into(),
+            url: Url::parse("https://example.com/movie.swf").unwrap(),
+            player,
+        };
+        let settings = ItemSettings {
+            open_url_mode: Some(OpenURLMode::Deny),
+            socket_mode: Some(SocketMode::Allow),
+            socket_allowed: vec!["example.com:1234".into(), "127.0.0.1:80".into()],
+            socket_denied: vec!["evil.com:666".into()],
+            layout: Some(ViewportLayout::FullBleed),
+        };
+        (info, settings)
+    }
+
+    fn assert_same(actual: &BundleInformation, expected: &BundleInformation) {
+        assert_eq!(actual.name, expected.name);
+        assert_eq!(actual.url, expected.url);
+        let (actual, expected) = (&actual.player, &expected.player);
+        assert_eq!(actual.parameters, expected.parameters);
+        assert_eq!(
+            actual.max_execution_duration,
+            expected.max_execution_duration
+        );
+        assert_eq!(actual.base, expected.base);
+        assert_eq!(actual.quality, expected.quality);
+        assert_eq!(actual.align, expected.align);
+        assert_eq!(actual.force_align, expected.force_align);
+        assert_eq!(actual.scale, expected.scale);
+        assert_eq!(actual.force_scale, expected.force_scale);
+        assert_eq!(actual.upgrade_to_https, expected.upgrade_to_https);
+        assert_eq!(actual.load_behavior, expected.load_behavior);
+        assert_eq!(actual.letterbox, expected.letterbox);
+        assert_eq!(actual.spoof_url, expected.spoof_url);
+        assert_eq!(actual.referer, expected.referer);
+        assert_eq!(actual.cookie, expected.cookie);
+        assert_eq!(actual.player_version, expected.player_version);
+        assert_eq!(actual.player_runtime, expected.player_runtime);
+        assert_eq!(actual.frame_rate, expected.frame_rate);
+        assert_eq!(
+            actual.dummy_external_interface,
+            expected.dummy_external_interface
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let (info, settings) = populated();
+        let (read_info, read_settings) = read_settings(&write_settings(&info, &settings)).unwrap();
+        assert_same(&read_info, &info);
+        assert_eq!(read_settings, settings);
+    }
+
+    #[test]
+    fn round_trip_defaults() {
+        let (mut info, _) = populated();
+        info.player = PlayerOptions::default();
+        let written = write_settings(&info, &ItemSettings::default());
+        assert!(!written.contains("[ios]"));
+        let (read_info, read_settings) = read_settings(&written).unwrap();
+        assert_same(&read_info, &info);
+        assert_eq!(read_settings, ItemSettings::default());
+    }
+
+    #[test]
+    fn update_preserves_unknown_keys() {
+        let existing = "\
+# Edited by hand
+[bundle]
+name = \"Old\"
+url = \"file:///old.swf\"
+future_key = 1
+
+[player]
+cookie = \"stale\"
+some_new_option = \"kept\"
+
+[ios]
+layout = \"safe_area\"
+other = true
+";
+        let (info, settings) = populated();
+        let updated = update_settings(existing, &info, &settings);
+
+        let document: DocumentMut = updated.parse().unwrap();
+        assert!(updated.starts_with("# Edited by hand\n"));
+        assert_eq!(document["bundle"]["future_key"].as_integer(), Some(1));
+        assert_eq!(document["player"]["some_new_option"].as_str(), Some("kept"));
+        assert_eq!(document["ios"]["other"].as_bool(), Some(true));
+
+        let (read_info, read_settings) = read_settings(&updated).unwrap();
+        assert_same(&read_info, &info);
+        assert_eq!(read_settings, settings);
+    }
+
+    #[test]
+    fn update_removes_cleared_keys() {
+        let (info, settings) = populated();
+        let existing = write_settings(&info, &settings);
+
+        let mut cleared = info.clone();
+        cleared.player = PlayerOptions::default();
+        let updated = update_settings(&existing, &cleared, &ItemSettings::default());
+
+        let document: DocumentMut = updated.parse().unwrap();
+        assert!(document["player"].as_table().unwrap().is_empty());
+        assert!(document.get(ITEM_SETTINGS_TABLE).is_none());
+        let (read_info, read_settings) = read_settings(&updated).unwrap();
+        assert_same(&read_info, &cleared);
+        assert_eq!(read_settings, ItemSettings::default());
+    }
+
+    #[test]
+    fn update_unparseable() {
+        let (info, settings) = populated();
+        assert_eq!(
+            update_settings("not [valid toml", &info, &settings),
+            write_settings(&info, &settings)
+        );
+    }
+}
@@ -0,0 +1,172 @@
+//! SharedObject storage on disk.
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use ruffle_core::backend::storage::StorageBackend;
+
+/// Stores each SharedObject as a `.sol` file inside a directory.
+///
+/// Used with a library item's `app_data/` directory, so that data persists
+/// across restarts, and is isolated between items.
+#[derive(Debug)]
+pub struct DiskStorageBackend {
+    base_path: PathBuf,
+}
+
+impl DiskStorageBackend {
+    pub fn new(base_path: impl Into<PathBuf>) -> Self {
+        Self {
+            base_path: base_path.into(),
+        }
+    }
+
+    /// Map a SharedObject name (e.g. `localhost/path/to/movie.swf/name`) to
+    /// a path inside the base directory.
+    ///
+    /// Returns `None` if the name is empty, or would escape the base
+    /// directory.
+    fn shared_object_path(&self, name: &str) -> Option<PathBuf> {
+        // Would be a file named just `.sol`
+        if name.is_empty() || name.ends_with('/') {
+            tracing::warn!(name, "rejected SharedObject name");
+            return None;
+        }
+        let mut path = self.base_path.clone();
+        let mut has_component = false;
+        for component in Path::new(&format!("{name}.sol")).components() {
+            match component {
+                Component::Normal(part) => {
+                    path.push(part);
+                    has_component = true;
+                }
+                // Allow `foo/./bar` and `foo//bar`
+                Component::CurDir => {}
+                Component::ParentDir | Component::RootDir | Component::Prefix(_) => {
+                    tracing::warn!(name, "rejected SharedObject name");
+                    return None;
+                }
+            }
+        }
+        has_component.then_some(path)
+    }
+}
+
+impl StorageBackend for DiskStorageBackend {
+    fn get(&self, name: &str) -> Option<Vec<u8>> {
+        let path = self.shared_object_path(name)?;
+        match fs::read(&path) {
+            Ok(data) => Some(data),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+            Err(err) => {
+                tracing::warn!(?path, "failed reading SharedObject: {err}");
+                None
+            }
+        }
+    }
+
+    fn put(&mut self, name: &str, value: &[u8]) -> bool {
+        let Some(path) = self.shared_object_path(name) else {
+            return false;
+        };
+        if let Some(parent) = path.parent() {
+            if let Err(err) = fs::create_dir_all(parent) {
+                tracing::warn!(?parent, "failed creating SharedObject directory: {err}");
+                return false;
+            }
+        }
+        // Write to a temporary file first, to avoid corrupting existing data
+        // if we're interrupted.
+        let tmp_path = path.with_extension("sol.tmp");
+        match fs::write(&tmp_path, value).and_then(|()| fs::rename(&tmp_path, &path)) {
+            Ok(()) => true,
+            Err(err) => {
+                tracing::warn!(?path, "failed writing SharedObject: {err}");
+                false
+            }
+        }
+    }
+
+    fn remove_key(&mut self, name: &str) {
+        let Some(path) = self.shared_object_path(name) else {
+            return;
+        };
+        match fs::remove_file(&path) {
+            Ok(()) => {}
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => tracing::warn!(?path, "failed removing SharedObject: {err}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn persists_across_instances() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut storage = DiskStorageBackend::new(dir.path());
+        assert!(storage.put("localhost/movie.swf/save", b"data"));
+        assert_eq!(storage.get("localhost/movie.swf/save").unwrap(), b"data");
+
+        // As if the app was restarted
+        let mut storage = DiskStorageBackend::new(dir.path());
+        assert_eq!(storage.get("localhost/movie.swf/save").unwrap(), b"data");
+        assert!(dir.path().join("localhost/movie.swf/save.sol").is_file());
+        assert!(!dir.path().join("localhost/movie.swf/save.sol.tmp").exists());
+
+        // Overwritten and removed
+        assert!(storage.put("localhost/movie.swf/save", b"new"));
+        assert_eq!(storage.get("localhost/movie.swf/save").unwrap(), b"new");
+        storage.remove_key("localhost/movie.swf/save");
+        assert_eq!(storage.get("localhost/movie.swf/save"), None);
+        // Removing again is fine
+        storage.remove_key("localhost/movie.swf/save");
+    }
+
+    #[test]
+    fn isolated_between_bundles() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut a = DiskStorageBackend::new(dir.path().join("a"));
+        let mut b = DiskStorageBackend::new(dir.path().join("b"));
+        assert!(a.put("localhost/movie.swf/save", b"a"));
+        assert_eq!(b.get("localhost/movie.swf/save"), None);
+        assert!(b.put("localhost/movie.swf/save", b"b"));
+        assert_eq!(a.get("localhost/movie.swf/save").unwrap(), b"a");
+        assert_eq!(b.get("localhost/movie.swf/save").unwrap(), b"b");
+    }
+
+    #[test]
+    fn path() {
+        let storage = DiskStorageBackend::new("/data");
+        assert_eq!(
+            storage.shared_object_path("localhost/movie.swf/save"),
+            Some(PathBuf::from("/data/localhost/movie.swf/save.sol"))
+        );
+        assert_eq!(
+            storage.shared_object_path("localhost/./movie.swf//save"),
+            Some(PathBuf::from("/data/localhost/movie.swf/save.sol"))
+        );
+    }
+
+    #[test]
+    fn rejected_names() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = dir.path().join("app_data");
+        let mut storage = DiskStorageBackend::new(&base);
+        for name in [
+            "",
+            "localhost/",
+            "../escape",
+            "localhost/../../escape",
+            "/escape",
+        ] {
+            assert_eq!(storage.shared_object_path(name), None, "{name:?}");
+            assert!(!storage.put(name, b"data"), "{name:?}");
+            assert_eq!(storage.get(name), None, "{name:?}");
+        }
+        assert!(!dir.path().join("escape.sol").exists());
+        assert!(!base.exists());
+    }
+}
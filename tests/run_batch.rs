@@ -0,0 +1,30 @@
+//! Running the `run_batch` binary end to end.
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+#[test]
+fn json_report_on_stdout() {
+    let dir = tempfile::tempdir().unwrap();
+    let movie = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("Assets.xcassets/logo-anim.dataset/logo-anim.swf");
+    fs::copy(movie, dir.path().join("logo-anim.swf")).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_run_batch"))
+        .args(["--frames", "1"])
+        .arg(dir.path())
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    // Logs from both processes go to stderr, so stdout is only the report
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["passed"], 1);
+    assert_eq!(report["failed"], 0);
+    assert_eq!(report["movies"][0]["name"], "logo-anim.swf");
+    assert_eq!(report["movies"][0]["status"], "passed");
+}